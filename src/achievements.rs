@@ -0,0 +1,335 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    locale::{Locale, Translator, UiFonts},
+    menu::{MenuButtonAction, MenuDescription, MenuItem},
+    mode::CurrentRun,
+    score::{RunStats, Score},
+    snake::{Direction, FoodEatenEvent, FoodKind, Snake, SnakeMovedEvent},
+    storage,
+    theme::{MenuPanel, Theme, ThemedText},
+};
+
+const PROGRESS_FILE: &str = "achievements.ron";
+
+// 解锁条件，单局的条件取历史最好成绩作为进度
+#[derive(Deserialize, Clone, Copy)]
+pub enum Goal {
+    // 单局蛇身长度
+    Length(u32),
+    // 单局存活秒数
+    SurviveSeconds(u32),
+    // 单局得分
+    Score(u32),
+    // 单局中连续吃到的食物数，期间不能连续两步都转弯
+    StraightFood(u32),
+    // 累计吃到的食物
+    TotalFood(u32),
+    // 累计吃到的金色食物
+    GoldenFood(u32),
+}
+
+impl Goal {
+    fn target(&self) -> u32 {
+        match *self {
+            Goal::Length(n)
+            | Goal::SurviveSeconds(n)
+            | Goal::Score(n)
+            | Goal::StraightFood(n)
+            | Goal::TotalFood(n)
+            | Goal::GoldenFood(n) => n,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Achievement {
+    pub id: String,
+    pub goal: Goal,
+}
+
+impl Achievement {
+    // 字符串表中的 key
+    pub fn name(&self) -> String {
+        format!("achievement.{}", self.id)
+    }
+    pub fn description(&self) -> String {
+        format!("achievement.{}.desc", self.id)
+    }
+}
+
+// 成就定义，来自 assets/achievements.ron
+#[derive(Resource, Deserialize)]
+pub struct Achievements {
+    achievements: Vec<Achievement>,
+}
+
+// 定义编译进程序，保证事件发生时已经可用
+impl Default for Achievements {
+    fn default() -> Self {
+        ron::from_str(include_str!("../assets/achievements.ron"))
+            .expect("invalid assets/achievements.ron")
+    }
+}
+
+// 已解锁的成就和进度，保存在数据目录
+#[derive(Resource, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AchievementProgress {
+    unlocked: HashSet<String>,
+    best: HashMap<String, u32>,
+    total_food: u32,
+    golden_food: u32,
+}
+
+impl AchievementProgress {
+    fn is_unlocked(&self, achievement: &Achievement) -> bool {
+        self.unlocked.contains(&achievement.id)
+    }
+
+    fn progress(&self, achievement: &Achievement) -> u32 {
+        match achievement.goal {
+            Goal::TotalFood(_) => self.total_food,
+            Goal::GoldenFood(_) => self.golden_food,
+            _ => self.best.get(&achievement.id).copied().unwrap_or(0),
+        }
+    }
+}
+
+// 本局中用于判断成就的状态
+#[derive(Resource, Default)]
+pub struct AchievementTracker {
+    last_direction: Option<Direction>,
+    last_turned: bool,
+    straight_food: u32,
+}
+
+pub struct AchievementUnlockedEvent(pub String);
+
+// 解锁提示，显示一段时间后消失
+#[derive(Component)]
+pub struct AchievementToast(Timer);
+
+pub fn setup_achievements(mut commands: Commands) {
+    commands.insert_resource(storage::load::<AchievementProgress>(PROGRESS_FILE));
+}
+
+pub fn reset_achievement_tracker(mut tracker: ResMut<AchievementTracker>) {
+    *tracker = AchievementTracker::default();
+}
+
+pub fn track_achievements(
+    mut moved_events: EventReader<SnakeMovedEvent>,
+    mut food_events: EventReader<FoodEatenEvent>,
+    mut tracker: ResMut<AchievementTracker>,
+    mut progress: ResMut<AchievementProgress>,
+    run: Res<CurrentRun>,
+) {
+    for event in moved_events.iter() {
+        let turned = tracker
+            .last_direction
+            .is_some_and(|direction| direction != event.direction);
+        if turned && tracker.last_turned {
+            tracker.straight_food = 0;
+        }
+        tracker.last_turned = turned;
+        tracker.last_direction = Some(event.direction);
+    }
+    for event in food_events.iter() {
+        tracker.straight_food += 1;
+        // 不计入排行榜的一局也不累计进度
+        if !run.counted {
+            continue;
+        }
+        progress.total_food += 1;
+        if event.kind == FoodKind::Golden {
+            progress.golden_food += 1;
+        }
+    }
+}
+
+// 游戏中检查成就进度，达到目标时解锁并保存
+pub fn unlock_achievements(
+    achievements: Res<Achievements>,
+    tracker: Res<AchievementTracker>,
+    stats: Res<RunStats>,
+    score: Res<Score>,
+    snake_query: Query<&Children, With<Snake>>,
+    mut progress: ResMut<AchievementProgress>,
+    mut events: EventWriter<AchievementUnlockedEvent>,
+    run: Res<CurrentRun>,
+) {
+    if !run.counted {
+        return;
+    }
+    let length = snake_query
+        .iter()
+        .map(|children| children.len())
+        .sum::<usize>() as u32;
+    let mut unlocked = false;
+    for achievement in &achievements.achievements {
+        if progress.is_unlocked(achievement) {
+            continue;
+        }
+        let current = match achievement.goal {
+            Goal::Length(_) => length,
+            Goal::SurviveSeconds(_) => stats.time as u32,
+            Goal::Score(_) => score.0,
+            Goal::StraightFood(_) => tracker.straight_food,
+            Goal::TotalFood(_) | Goal::GoldenFood(_) => progress.progress(achievement),
+        };
+        let best = progress.best.entry(achievement.id.clone()).or_default();
+        *best = (*best).max(current);
+        if current >= achievement.goal.target() {
+            progress.unlocked.insert(achievement.id.clone());
+            events.send(AchievementUnlockedEvent(achievement.id.clone()));
+            unlocked = true;
+        }
+    }
+    if unlocked {
+        storage::save(PROGRESS_FILE, &*progress);
+    }
+}
+
+// 一局结束时保存进度
+pub fn save_achievements(progress: Res<AchievementProgress>) {
+    storage::save(PROGRESS_FILE, &*progress);
+}
+
+pub fn spawn_achievement_toasts(
+    mut commands: Commands,
+    mut events: EventReader<AchievementUnlockedEvent>,
+    achievements: Res<Achievements>,
+    asset_server: Res<AssetServer>,
+    locale: Res<Locale>,
+    fonts: Res<UiFonts>,
+    theme: Res<Theme>,
+    toast_query: Query<(), With<AchievementToast>>,
+) {
+    let translator = Translator {
+        locale: &locale,
+        fonts: &fonts,
+        asset_server: &asset_server,
+    };
+    let style = TextStyle {
+        font: fonts.bold.clone(),
+        font_size: 22.0,
+        color: theme.title_text,
+    };
+    // 同时解锁多个成就时依次向下排列
+    let mut row = toast_query.iter().count();
+    for AchievementUnlockedEvent(id) in events.iter() {
+        let Some(achievement) = achievements.achievements.iter().find(|a| &a.id == id) else {
+            continue;
+        };
+        info!("achievement unlocked: {}", id);
+        let mut title = translator.section("achievement.toast", &style);
+        title.value.push_str(": ");
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            top: Val::Px(60.0 + row as f32 * 40.0),
+                            left: Val::Px(0.0),
+                            right: Val::Px(0.0),
+                            ..default()
+                        },
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                },
+                AchievementToast(Timer::from_seconds(3.0, TimerMode::Once)),
+            ))
+            .with_children(|parent| {
+                parent
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                padding: UiRect::all(Val::Px(6.0)),
+                                ..default()
+                            },
+                            background_color: theme.menu_background.into(),
+                            ..default()
+                        },
+                        MenuPanel,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_sections([
+                                title,
+                                translator.section(&achievement.name(), &style),
+                            ]),
+                            ThemedText::Title,
+                        ));
+                    });
+            });
+        row += 1;
+    }
+}
+
+pub fn update_achievement_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut toast_query: Query<(Entity, &mut AchievementToast, &Children)>,
+    mut panel_query: Query<(&mut BackgroundColor, &Children), With<MenuPanel>>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (entity, mut toast, children) in &mut toast_query {
+        if toast.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        // 最后半秒淡出
+        let alpha = (toast.0.remaining_secs() / 0.5).min(1.0);
+        for panel in children {
+            let Ok((mut background, texts)) = panel_query.get_mut(*panel) else {
+                continue;
+            };
+            background.0.set_a(alpha);
+            for text in texts {
+                if let Ok(mut text) = text_query.get_mut(*text) {
+                    for section in &mut text.sections {
+                        section.style.color.set_a(alpha);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// 成就列表：名称、进度和说明
+pub fn achievements_menu(
+    achievements: Res<Achievements>,
+    progress: Res<AchievementProgress>,
+) -> MenuDescription {
+    let mut items = Vec::new();
+    for achievement in &achievements.achievements {
+        let status = if progress.is_unlocked(achievement) {
+            "achievement.unlocked".to_string()
+        } else {
+            format!(
+                "{}/{}",
+                progress.progress(achievement),
+                achievement.goal.target()
+            )
+        };
+        items.push(MenuItem::Stat(achievement.name(), status));
+        items.push(MenuItem::Stat(achievement.description(), String::new()));
+    }
+    items.push(MenuItem::Button(
+        "menu.back".to_string(),
+        MenuButtonAction::BackToMainMenu,
+    ));
+    MenuDescription {
+        title: "menu.achievements.title".to_string(),
+        items,
+        back: Some(MenuButtonAction::BackToMainMenu),
+    }
+}
@@ -0,0 +1,19 @@
+// 排行榜服务器的参考实现
+// 用法：score-server [监听地址] [保存文件]，默认 127.0.0.1:8080 和 scores.json
+use std::net::TcpListener;
+
+use snake::score_server::ScoreServer;
+
+fn main() -> std::io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let addr = args.next().unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let path = args.next().unwrap_or_else(|| "scores.json".to_string());
+    let server = ScoreServer::open(&path)?;
+    let listener = TcpListener::bind(&addr)?;
+    println!(
+        "score server listening on http://{} (saving to {})",
+        listener.local_addr()?,
+        path
+    );
+    server.run(listener)
+}
@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+
+use crate::{
+    settings::Settings,
+    snake::{SQUARE_SIZE, X, Y},
+    theme::Theme,
+};
+
+// 边框宽度和顶部分数栏高度
+pub const BORDER_WIDTH: f32 = 6.0;
+pub const HUD_HEIGHT: f32 = 60.0;
+
+// 可以移动的区域，横向 -X..=X，纵向 -Y..=Y
+pub const BOARD_WIDTH: f32 = (2 * X + 1) as f32 * SQUARE_SIZE;
+pub const BOARD_HEIGHT: f32 = (2 * Y + 1) as f32 * SQUARE_SIZE;
+
+pub const WINDOW_WIDTH: f32 = BOARD_WIDTH + 2.0 * BORDER_WIDTH;
+pub const WINDOW_HEIGHT: f32 = BOARD_HEIGHT + 2.0 * BORDER_WIDTH + HUD_HEIGHT;
+
+// 摄像机上移，给顶部的分数栏留出空间，游戏区域不被遮挡
+pub const CAMERA_OFFSET: Vec3 = Vec3::new(0.0, HUD_HEIGHT / 2.0, 0.0);
+
+#[derive(Component)]
+pub struct BoardBorder;
+
+#[derive(Component)]
+pub struct GridCell;
+
+pub fn setup_board(mut commands: Commands, theme: Res<Theme>) {
+    // 上下左右四条边框
+    let horizontal = Vec2::new(BOARD_WIDTH + 2.0 * BORDER_WIDTH, BORDER_WIDTH);
+    let vertical = Vec2::new(BORDER_WIDTH, BOARD_HEIGHT);
+    let x_edge = (BOARD_WIDTH + BORDER_WIDTH) / 2.0;
+    let y_edge = (BOARD_HEIGHT + BORDER_WIDTH) / 2.0;
+    for (position, size) in [
+        (Vec2::new(0.0, y_edge), horizontal),
+        (Vec2::new(0.0, -y_edge), horizontal),
+        (Vec2::new(-x_edge, 0.0), vertical),
+        (Vec2::new(x_edge, 0.0), vertical),
+    ] {
+        commands.spawn((
+            SpriteBundle {
+                transform: Transform::from_translation(position.extend(-1.0)),
+                sprite: Sprite {
+                    color: theme.border,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                ..default()
+            },
+            BoardBorder,
+        ));
+    }
+
+    // 棋盘格背景，和格子对齐
+    for x in -X..=X {
+        for y in -Y..=Y {
+            if (x + y) % 2 != 0 {
+                continue;
+            }
+            commands.spawn((
+                SpriteBundle {
+                    transform: Transform::from_xyz(
+                        x as f32 * SQUARE_SIZE,
+                        y as f32 * SQUARE_SIZE,
+                        -2.0,
+                    ),
+                    sprite: Sprite {
+                        color: theme.grid,
+                        custom_size: Some(Vec2::new(SQUARE_SIZE, SQUARE_SIZE)),
+                        ..default()
+                    },
+                    ..default()
+                },
+                GridCell,
+            ));
+        }
+    }
+}
+
+pub fn toggle_grid(settings: Res<Settings>, mut query: Query<&mut Visibility, With<GridCell>>) {
+    if !settings.is_changed() {
+        return;
+    }
+    for mut visibility in &mut query {
+        visibility.is_visible = settings.show_grid;
+    }
+}
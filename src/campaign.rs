@@ -0,0 +1,341 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    level::Level,
+    locale::{Locale, Localized, UiFonts},
+    menu::{MenuButtonAction, MenuDescription, MenuItem},
+    mode::{CurrentRun, GameMode},
+    score::{hud_item_style, HighScores, HudRow, Record, RunStats, Score},
+    snake::{GameEnd, Snake},
+    storage,
+    theme::{Theme, ThemedText},
+};
+
+const PROGRESS_FILE: &str = "campaign.ron";
+
+// 过关条件
+#[derive(Deserialize, Clone, Copy)]
+pub enum CampaignGoal {
+    // 蛇身长度
+    Length(u32),
+    // 吃到的食物数
+    Food(u32),
+    // 存活秒数
+    Survive(u32),
+}
+
+impl CampaignGoal {
+    pub fn target(&self) -> u32 {
+        match *self {
+            CampaignGoal::Length(n) | CampaignGoal::Food(n) | CampaignGoal::Survive(n) => n,
+        }
+    }
+
+    // 字符串表中的 key
+    fn label(&self) -> &'static str {
+        match *self {
+            CampaignGoal::Length(_) => "campaign.goal.length",
+            CampaignGoal::Food(_) => "campaign.goal.food",
+            CampaignGoal::Survive(_) => "campaign.goal.survive",
+        }
+    }
+
+    pub fn progress(&self, stats: &RunStats, length: usize) -> u32 {
+        match *self {
+            CampaignGoal::Length(_) => length as u32,
+            CampaignGoal::Food(_) => stats.food_eaten.values().sum(),
+            CampaignGoal::Survive(_) => stats.time as u32,
+        }
+    }
+
+    // 距离达成存活目标还有多久，其他目标返回 None
+    pub fn time_left(&self, stats: &RunStats) -> Option<Duration> {
+        match *self {
+            CampaignGoal::Survive(n) if stats.time < n as f32 => {
+                Some(Duration::from_secs_f32(n as f32 - stats.time))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CampaignLevel {
+    pub id: String,
+    pub goal: CampaignGoal,
+    pub level: Level,
+}
+
+impl CampaignLevel {
+    // 字符串表中的 key
+    pub fn name(&self) -> String {
+        format!("campaign.{}", self.id)
+    }
+
+    // 例如 "Food 5"
+    fn describe(&self, locale: &Locale) -> String {
+        let unit = if matches!(self.goal, CampaignGoal::Survive(_)) {
+            "s"
+        } else {
+            ""
+        };
+        format!(
+            "{} {}{}",
+            locale.get(self.goal.label()),
+            self.goal.target(),
+            unit
+        )
+    }
+}
+
+// 关卡定义，来自 assets/campaign.ron
+#[derive(Resource, Deserialize)]
+pub struct Campaign {
+    levels: Vec<CampaignLevel>,
+}
+
+// 和成就一样编译进程序
+impl Default for Campaign {
+    fn default() -> Self {
+        ron::from_str(include_str!("../assets/campaign.ron")).expect("invalid assets/campaign.ron")
+    }
+}
+
+impl Campaign {
+    pub fn level(&self, index: usize) -> Option<&CampaignLevel> {
+        self.levels.get(index)
+    }
+}
+
+// 已完成的关卡，保存在数据目录
+#[derive(Resource, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CampaignProgress {
+    completed: HashSet<String>,
+}
+
+impl CampaignProgress {
+    fn is_completed(&self, level: &CampaignLevel) -> bool {
+        self.completed.contains(&level.id)
+    }
+
+    // 第一关总是解锁，之后的关卡需要完成前一关
+    fn is_unlocked(&self, campaign: &Campaign, index: usize) -> bool {
+        index == 0
+            || campaign
+                .level(index - 1)
+                .is_some_and(|level| self.is_completed(level))
+    }
+}
+
+#[derive(Component)]
+pub struct Goalboard;
+
+pub fn setup_campaign(
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    theme: Res<Theme>,
+    row: Res<HudRow>,
+) {
+    commands.insert_resource(storage::load::<CampaignProgress>(PROGRESS_FILE));
+
+    // 闯关目标和进度，只在闯关模式显示
+    let board = commands
+        .spawn(
+            TextBundle::from_sections([
+                TextSection::new(
+                    "Goal: ",
+                    TextStyle {
+                        font: fonts.bold.clone(),
+                        font_size: 30.0,
+                        color: theme.hud_label,
+                    },
+                ),
+                TextSection::new(
+                    "",
+                    TextStyle {
+                        font: fonts.mono.clone(),
+                        font_size: 30.0,
+                        color: theme.hud_value,
+                    },
+                ),
+            ])
+            .with_style(hud_item_style()),
+        )
+        .insert((
+            Goalboard,
+            ThemedText::Hud,
+            Localized("hud.goal".to_string()),
+        ))
+        .id();
+    commands.entity(row.0).add_child(board);
+}
+
+pub fn update_goalboard(
+    run: Res<CurrentRun>,
+    campaign: Res<Campaign>,
+    stats: Res<RunStats>,
+    locale: Res<Locale>,
+    snake_query: Query<&Children, With<Snake>>,
+    mut query: Query<(&mut Text, &mut Style), With<Goalboard>>,
+) {
+    let (mut text, mut style) = query.single_mut();
+    let level = match run.mode {
+        GameMode::Campaign(index) => campaign.level(index),
+        _ => None,
+    };
+    let display = if level.is_some() {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    // 只在变化时修改，避免每帧重新布局
+    if style.display != display {
+        style.display = display;
+    }
+    let Some(level) = level else {
+        return;
+    };
+    let length = snake_query.iter().map(|children| children.len()).sum();
+    text.sections[1].value = format!(
+        "{} {}/{}",
+        locale.get(level.goal.label()),
+        level.goal.progress(&stats, length).min(level.goal.target()),
+        level.goal.target()
+    );
+}
+
+// 达到目标时结束这一局，记录成绩并进入过关界面
+pub fn check_campaign_goal(
+    run: Res<CurrentRun>,
+    campaign: Res<Campaign>,
+    score: Res<Score>,
+    mut stats: ResMut<RunStats>,
+    mut record: ResMut<Record>,
+    mut high_scores: ResMut<HighScores>,
+    mut progress: ResMut<CampaignProgress>,
+    snake_query: Query<&Children, With<Snake>>,
+    mut game_end: GameEnd,
+) {
+    let GameMode::Campaign(index) = run.mode else {
+        return;
+    };
+    let Some(level) = campaign.level(index) else {
+        return;
+    };
+    let length = snake_query.iter().map(|children| children.len()).sum();
+    if level.goal.progress(&stats, length) < level.goal.target() || !game_end.complete() {
+        return;
+    }
+    info!("campaign level {} complete", level.id);
+    stats.length = length;
+    // 用过调试控制台的一局照常过关，但不解锁下一关，也不算新纪录
+    if !run.counted {
+        return;
+    }
+    progress.completed.insert(level.id.clone());
+    storage::save(PROGRESS_FILE, &*progress);
+
+    stats.new_record = score.0 > record.0;
+    if stats.new_record {
+        record.0 = score.0;
+    }
+    stats.rank = high_scores.submit(&run.mode.table(), score.0);
+}
+
+// 选关界面：已解锁的关卡可以点击，显示目标或最好成绩
+pub fn level_select_menu(
+    campaign: Res<Campaign>,
+    progress: Res<CampaignProgress>,
+    high_scores: Res<HighScores>,
+    locale: Res<Locale>,
+) -> MenuDescription {
+    let mut items = Vec::new();
+    for (index, level) in campaign.levels.iter().enumerate() {
+        if !progress.is_unlocked(&campaign, index) {
+            items.push(MenuItem::Stat(level.name(), "campaign.locked".to_string()));
+            continue;
+        }
+        let status = if progress.is_completed(level) {
+            format!(
+                "{}, {} {}",
+                locale.get("campaign.completed"),
+                locale.get("campaign.best"),
+                high_scores.best(&GameMode::Campaign(index).table())
+            )
+        } else {
+            level.describe(&locale)
+        };
+        items.push(MenuItem::Entry(
+            level.name(),
+            status,
+            MenuButtonAction::StartGame(GameMode::Campaign(index)),
+        ));
+    }
+    items.push(MenuItem::Button(
+        "menu.back".to_string(),
+        MenuButtonAction::BackToMainMenu,
+    ));
+    MenuDescription {
+        title: "menu.campaign.title".to_string(),
+        items,
+        back: Some(MenuButtonAction::BackToMainMenu),
+    }
+}
+
+pub fn level_complete_menu(
+    run: Res<CurrentRun>,
+    campaign: Res<Campaign>,
+    score: Res<Score>,
+    stats: Res<RunStats>,
+) -> MenuDescription {
+    let stat = |label: &str, value: String| MenuItem::Stat(label.to_string(), value);
+    let GameMode::Campaign(index) = run.mode else {
+        return MenuDescription {
+            title: "menu.level_complete.title".to_string(),
+            items: Vec::new(),
+            back: Some(MenuButtonAction::BackToMainMenu),
+        };
+    };
+    let seconds = stats.time as u32;
+    let mut items = Vec::new();
+    if let Some(level) = campaign.level(index) {
+        items.push(stat("summary.level", level.name()));
+    }
+    items.extend([
+        stat("summary.score", score.0.to_string()),
+        stat("summary.length", stats.length.to_string()),
+        stat(
+            "summary.time",
+            format!("{}:{:02}", seconds / 60, seconds % 60),
+        ),
+    ]);
+    if stats.new_record {
+        items.push(stat("summary.new_record", String::new()));
+    }
+    if campaign.level(index + 1).is_some() {
+        items.push(MenuItem::Button(
+            "menu.next_level".to_string(),
+            MenuButtonAction::StartGame(GameMode::Campaign(index + 1)),
+        ));
+    }
+    items.extend([
+        MenuItem::Button("menu.restart".to_string(), MenuButtonAction::RestartGame),
+        MenuItem::Button(
+            "menu.level_select".to_string(),
+            MenuButtonAction::OpenCampaign,
+        ),
+        MenuItem::Button(
+            "menu.main_menu".to_string(),
+            MenuButtonAction::BackToMainMenu,
+        ),
+    ]);
+    MenuDescription {
+        title: "menu.level_complete.title".to_string(),
+        items,
+        back: Some(MenuButtonAction::OpenCampaign),
+    }
+}
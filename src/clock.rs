@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    campaign::Campaign,
+    hazard::Hazard,
+    level::Level,
+    mode::{CurrentRun, GameMode},
+    score::RunStats,
+    shrink::Arena,
+    snake::{FoodTimer, Snake},
+    time_attack::Countdown,
+};
+
+// 计时器的浮点误差远小于这个值，相差不到这么多的事件算作同一时刻
+const SAME_INSTANT: Duration = Duration::from_millis(1);
+// 帧率太低、落后太多时不再逐个处理，一帧推进全部时间
+const MAX_LAG: Duration = Duration::from_millis(250);
+
+// 游戏逻辑使用的时间：每帧最多推进到下一个定时事件（移动、生成食物、障碍、缩圈、
+// 倒计时和存活目标），剩下的时间留到下一帧。不同时刻的事件不会挤在同一帧中，
+// 处理顺序和 verify 中按理想时间的模拟一致
+#[derive(Resource, Default)]
+pub struct GameClock {
+    delta: Duration,
+    // 还没有推进的时间
+    lag: Duration,
+}
+
+impl GameClock {
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+}
+
+pub fn reset_game_clock(mut clock: ResMut<GameClock>) {
+    *clock = GameClock::default();
+}
+
+pub fn advance_game_clock(
+    time: Res<Time>,
+    mut clock: ResMut<GameClock>,
+    snake_query: Query<&Snake>,
+    food_timer_query: Query<&FoodTimer>,
+    hazard_query: Query<&Hazard>,
+    arena: Res<Arena>,
+    level: Res<Level>,
+    countdown: Res<Countdown>,
+    run: Res<CurrentRun>,
+    campaign: Res<Campaign>,
+    stats: Res<RunStats>,
+) {
+    clock.lag += time.delta();
+    let goal = match run.mode {
+        GameMode::Campaign(index) => campaign.level(index).map(|level| level.goal),
+        _ => None,
+    };
+    let next = snake_query
+        .iter()
+        .map(|snake| snake.move_timer.remaining())
+        .chain(food_timer_query.iter().filter_map(FoodTimer::remaining))
+        .chain(hazard_query.iter().map(Hazard::remaining))
+        .chain(arena.remaining(&level))
+        .chain(countdown.remaining())
+        .chain(goal.and_then(|goal| goal.time_left(&stats)))
+        .min();
+    let clock = &mut *clock;
+    clock.delta = match next {
+        Some(next) if clock.lag < MAX_LAG => clock.lag.min(next + SAME_INSTANT),
+        _ => clock.lag,
+    };
+    clock.lag -= clock.delta;
+}
@@ -3,19 +3,92 @@ use bevy::prelude::*;
 pub const HEIGHT: f32 = 480.0;
 pub const WIDTH: f32 = 900.0;
 
-#[derive(Component, Clone, Debug, Hash, PartialEq, Eq)]
+// 整个游戏只有这一个状态，游戏中的细分状态放在 InGame 里
+// 切换状态都通过 Transition::transition，只允许 can_transition_to 中列出的切换
+#[derive(Component, Clone, Copy, Debug, Hash, PartialEq, Eq)]
 pub enum AppState {
     MainMenu,
-    InGame,
+    Settings,
+    Achievements,
+    HighScores,
+    ModeSelect,
+    LevelSelect,
+    Editor,
+    InGame(PlayState),
+    Dying,
     GameOver,
+    LevelComplete,
 }
 
-#[derive(Component, Clone, Debug, Hash, PartialEq, Eq)]
-pub enum GameState {
-    Playing,
-    Restarted,
-    Quitted,
-    Pause,
+#[derive(Component, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum PlayState {
+    // 开始新的一局，下一帧开始移动
+    Starting,
+    // 继续保存的一局，恢复后暂停
+    Resuming,
+    Running,
+    Paused,
+    // 练习模式中按住倒退键
+    Rewinding,
+}
+
+impl AppState {
+    pub fn can_transition_to(&self, next: &AppState) -> bool {
+        use AppState::*;
+        use PlayState::*;
+        matches!(
+            (self, next),
+            (
+                MainMenu,
+                Settings
+                    | Achievements
+                    | HighScores
+                    | ModeSelect
+                    | LevelSelect
+                    | Editor
+                    | InGame(Starting | Resuming)
+            ) | (Settings | Achievements | HighScores, MainMenu)
+                | (ModeSelect | LevelSelect | Editor, MainMenu | InGame(Starting))
+                | (InGame(Starting), InGame(Running))
+                // 存档读取失败时开始新的一局
+                | (InGame(Resuming), InGame(Starting | Paused))
+                | (InGame(Running), InGame(Paused | Rewinding) | Dying | LevelComplete)
+                | (InGame(Rewinding), InGame(Running | Paused))
+                | (InGame(Paused), InGame(Running | Starting) | MainMenu | Editor)
+                | (Dying, GameOver)
+                | (GameOver, InGame(Starting) | MainMenu | Editor | LevelSelect)
+                | (LevelComplete, InGame(Starting) | MainMenu | LevelSelect)
+        )
+    }
+}
+
+pub trait Transition {
+    // 切换成功时返回 true
+    // 不允许的切换只记录警告；同一帧里已经有别的切换时（例如同时撞墙和撞到障碍）忽略后来的
+    fn transition(&mut self, next: AppState) -> bool;
+}
+
+impl Transition for State<AppState> {
+    fn transition(&mut self, next: AppState) -> bool {
+        let current = *self.current();
+        if !current.can_transition_to(&next) {
+            warn!("ignored transition from {:?} to {:?}", current, next);
+            return false;
+        }
+        match self.set(next) {
+            Ok(()) => {
+                debug!("transition from {:?} to {:?}", current, next);
+                true
+            }
+            Err(err) => {
+                debug!(
+                    "ignored transition from {:?} to {:?}: {:?}",
+                    current, next, err
+                );
+                false
+            }
+        }
+    }
 }
 
 #[derive(Debug, Resource)]
@@ -38,4 +111,4 @@ pub fn setup_game_audios(mut command: Commands, asset_server: Res<AssetServer>)
         die: asset_server.load("sounds/die.wav"),
     };
     command.insert_resource(game_audios);
-}
\ No newline at end of file
+}
@@ -0,0 +1,334 @@
+use std::{str::FromStr, time::Duration};
+
+use bevy::{input::InputSystem, prelude::*};
+use bevy_inspector_egui::{
+    bevy_egui::{EguiContext, EguiPlugin},
+    bevy_inspector, egui, DefaultInspectorConfigPlugin,
+};
+
+use crate::{
+    common::{AppState, PlayState, Transition},
+    level::Level,
+    mode::{CurrentRun, NextSeed},
+    settings::Settings,
+    snake::{
+        grow_snake, spawn_food, Food, FoodAssets, FoodKind, Invincible, Point, Snake, SnakeAtlas,
+    },
+};
+
+const INSPECTOR_KEY: KeyCode = KeyCode::F12;
+const CONSOLE_KEY: KeyCode = KeyCode::Grave;
+// 控制台最多保留的行数
+const MAX_LINES: usize = 100;
+
+const HELP: &str = "commands:
+  speed <steps per second>
+  grow <n>
+  food <x> <y> [normal|golden|clock]
+  invincible
+  seed <n>  (used by the next run)
+  state <menu|settings|achievements|highscores|modes|levels|editor|
+         start|resume|running|paused|rewinding|dying|gameover|complete>";
+
+// 控制台命令，由 run_console_commands 执行
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConsoleCommand {
+    // 每秒走几格，只影响当前这条蛇
+    Speed(f32),
+    Grow(usize),
+    Food(Point, FoodKind),
+    Invincible,
+    Seed(u64),
+    // 仍然只允许 AppState::can_transition_to 中的切换
+    State(AppState),
+    Help,
+}
+
+impl FromStr for ConsoleCommand {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let number = |index: usize| -> Result<&str, String> {
+            words
+                .get(index)
+                .copied()
+                .ok_or_else(|| format!("missing argument for {}", words[0]))
+        };
+        let invalid = |word: &str| format!("invalid argument {word:?}");
+        match words.first().copied() {
+            Some("speed") => {
+                let word = number(1)?;
+                match word.parse::<f32>() {
+                    Ok(speed) if speed > 0.0 && speed.is_finite() => {
+                        Ok(ConsoleCommand::Speed(speed))
+                    }
+                    _ => Err(invalid(word)),
+                }
+            }
+            Some("grow") => {
+                let word = number(1)?;
+                word.parse()
+                    .map(ConsoleCommand::Grow)
+                    .map_err(|_| invalid(word))
+            }
+            Some("food") => {
+                let (x, y) = (number(1)?, number(2)?);
+                let x = x.parse().map_err(|_| invalid(x))?;
+                let y = y.parse().map_err(|_| invalid(y))?;
+                let kind = match words.get(3).copied() {
+                    None | Some("normal") => FoodKind::Normal,
+                    Some("golden") => FoodKind::Golden,
+                    Some("clock") => FoodKind::Clock,
+                    Some(word) => return Err(invalid(word)),
+                };
+                Ok(ConsoleCommand::Food(Point::new(x, y), kind))
+            }
+            Some("invincible") => Ok(ConsoleCommand::Invincible),
+            Some("seed") => {
+                let word = number(1)?;
+                word.parse()
+                    .map(ConsoleCommand::Seed)
+                    .map_err(|_| invalid(word))
+            }
+            Some("state") => {
+                let word = number(1)?;
+                let state = match word {
+                    "menu" => AppState::MainMenu,
+                    "settings" => AppState::Settings,
+                    "achievements" => AppState::Achievements,
+                    "highscores" => AppState::HighScores,
+                    "modes" => AppState::ModeSelect,
+                    "levels" => AppState::LevelSelect,
+                    "editor" => AppState::Editor,
+                    "start" => AppState::InGame(PlayState::Starting),
+                    "resume" => AppState::InGame(PlayState::Resuming),
+                    "running" => AppState::InGame(PlayState::Running),
+                    "paused" => AppState::InGame(PlayState::Paused),
+                    "rewinding" => AppState::InGame(PlayState::Rewinding),
+                    "dying" => AppState::Dying,
+                    "gameover" => AppState::GameOver,
+                    "complete" => AppState::LevelComplete,
+                    _ => return Err(format!("unknown state {word:?}")),
+                };
+                Ok(ConsoleCommand::State(state))
+            }
+            Some("help") => Ok(ConsoleCommand::Help),
+            Some(word) => Err(format!("unknown command {word:?}, try help")),
+            None => Err("empty command".to_string()),
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Console {
+    open: bool,
+    input: String,
+    lines: Vec<String>,
+}
+
+impl Console {
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    fn print(&mut self, line: impl Into<String>) {
+        print_line(&mut self.lines, line.into());
+    }
+}
+
+fn print_line(lines: &mut Vec<String>, line: String) {
+    lines.push(line);
+    if lines.len() > MAX_LINES {
+        lines.remove(0);
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Inspector {
+    open: bool,
+}
+
+// 只执行控制台命令，不显示界面，测试中也可以使用
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .add_event::<ConsoleCommand>()
+            .add_system(run_console_commands);
+    }
+}
+
+// 调试界面：F12 切换 World Inspector，` 键切换控制台
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(EguiPlugin)
+            .add_plugin(DefaultInspectorConfigPlugin)
+            .add_plugin(ConsolePlugin)
+            .init_resource::<Inspector>()
+            .add_system_to_stage(CoreStage::PreUpdate, block_game_input.after(InputSystem))
+            .add_system(toggle_debug_windows)
+            .add_system(console_ui.before(run_console_commands))
+            .add_system(inspector_ui);
+    }
+}
+
+// 在输入框里打字时游戏不响应按键
+fn block_game_input(
+    mut egui_context: ResMut<EguiContext>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+) {
+    if egui_context.ctx_mut().wants_keyboard_input() {
+        keyboard_input.reset_all();
+    }
+}
+
+fn toggle_debug_windows(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut inspector: ResMut<Inspector>,
+    mut console: ResMut<Console>,
+) {
+    if keyboard_input.just_pressed(INSPECTOR_KEY) {
+        inspector.open = !inspector.open;
+    }
+    if keyboard_input.just_pressed(CONSOLE_KEY) {
+        console.open = !console.open;
+    }
+}
+
+// 和 bevy_inspector_egui::quick::WorldInspectorPlugin 相同，关闭时不显示
+fn inspector_ui(world: &mut World) {
+    if !world.resource::<Inspector>().open {
+        return;
+    }
+    let egui_context = world.resource_mut::<EguiContext>().ctx_mut().clone();
+    let mut open = true;
+    egui::Window::new("World Inspector")
+        .open(&mut open)
+        .default_size((320.0, 160.0))
+        .show(&egui_context, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                bevy_inspector::ui_for_world(world, ui);
+                ui.allocate_space(ui.available_size());
+            });
+        });
+    world.resource_mut::<Inspector>().open = open;
+}
+
+fn console_ui(
+    mut egui_context: ResMut<EguiContext>,
+    mut console: ResMut<Console>,
+    mut commands: EventWriter<ConsoleCommand>,
+) {
+    let Console { open, input, lines } = &mut *console;
+    if !*open {
+        return;
+    }
+    egui::Window::new("Console")
+        .open(open)
+        .default_width(360.0)
+        .show(egui_context.ctx_mut(), |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for line in lines.iter() {
+                        ui.monospace(line);
+                    }
+                });
+            let response = ui.text_edit_singleline(input);
+            if response.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+                let text = std::mem::take(input);
+                print_line(lines, format!("> {text}"));
+                match text.parse() {
+                    Ok(command) => commands.send(command),
+                    Err(err) => print_line(lines, err),
+                }
+                response.request_focus();
+            }
+        });
+}
+
+pub fn run_console_commands(
+    mut commands: Commands,
+    mut events: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    mut snake_query: Query<(Entity, &Children, &mut Snake)>,
+    point_query: Query<&Point>,
+    food_query: Query<&Food>,
+    atlas: Res<SnakeAtlas>,
+    food_assets: Res<FoodAssets>,
+    settings: Res<Settings>,
+    level: Res<Level>,
+    mut run: ResMut<CurrentRun>,
+    mut invincible: ResMut<Invincible>,
+    mut next_seed: ResMut<NextSeed>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    for command in events.iter() {
+        match *command {
+            ConsoleCommand::Speed(speed) => {
+                for (_, _, mut snake) in &mut snake_query {
+                    snake
+                        .move_timer
+                        .set_duration(Duration::from_secs_f32(1.0 / speed));
+                }
+                run.counted = false;
+                console.print(format!("speed set to {speed} steps per second"));
+            }
+            ConsoleCommand::Grow(n) => {
+                for (parent, children, _) in &snake_query {
+                    let Some(&tail) = children.last().and_then(|tail| point_query.get(*tail).ok())
+                    else {
+                        continue;
+                    };
+                    grow_snake(&mut commands, &atlas, parent, tail, n);
+                }
+                run.counted = false;
+                console.print(format!("grew by {n}"));
+            }
+            ConsoleCommand::Food(point, kind) => {
+                // 不检查食物区域，方便测试边角的位置
+                let taken = !point.in_bounds()
+                    || level.is_blocked(&point)
+                    || point_query.iter().any(|other| *other == point)
+                    || food_query.iter().any(|food| food.point == point);
+                if taken {
+                    console.print(format!("cannot place food at {point}"));
+                    continue;
+                }
+                spawn_food(
+                    &mut commands,
+                    &food_assets,
+                    Food { point, kind },
+                    settings.effects,
+                );
+                run.counted = false;
+                console.print(format!("spawned food at {point}"));
+            }
+            ConsoleCommand::Invincible => {
+                invincible.0 = !invincible.0;
+                if invincible.0 {
+                    run.counted = false;
+                }
+                console.print(format!("invincible: {}", invincible.0));
+            }
+            ConsoleCommand::Seed(seed) => {
+                next_seed.0 = Some(seed);
+                console.print(format!("seed {seed} will be used by the next run"));
+            }
+            ConsoleCommand::State(state) => {
+                let current = *app_state.current();
+                if app_state.transition(state) {
+                    console.print(format!("{current:?} -> {state:?}"));
+                } else {
+                    console.print(format!("cannot go from {current:?} to {state:?}"));
+                }
+            }
+            ConsoleCommand::Help => console.print(HELP),
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use bevy::prelude::*;
+
+use crate::{
+    board::{CAMERA_OFFSET, HUD_HEIGHT},
+    common::{AppState, PlayState, Transition},
+    level::{portal_color, CustomLevel, Level},
+    locale::{Locale, Translator, UiFonts},
+    menu::{spawn_button, MenuActionEvent, MenuButtonAction},
+    mode::{CurrentRun, GameMode},
+    snake::{Point, SQUARE_SIZE},
+    storage,
+    theme::{MenuPanel, Theme, ThemedText},
+};
+
+// 编辑器保存和读取的关卡文件，在数据目录中
+const LEVEL_FILE: &str = "custom.level.ron";
+
+const TOOL_BUTTON_WIDTH: f32 = 72.0;
+
+// 鼠标左键使用的工具，右键总是擦除
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum EditorTool {
+    Wall,
+    Start,
+    Portal,
+    FoodZone,
+    Erase,
+}
+
+impl EditorTool {
+    const ALL: [EditorTool; 5] = [
+        EditorTool::Wall,
+        EditorTool::Start,
+        EditorTool::Portal,
+        EditorTool::FoodZone,
+        EditorTool::Erase,
+    ];
+
+    // 字符串表中的 key
+    fn label(&self) -> &'static str {
+        match *self {
+            EditorTool::Wall => "editor.tool.wall",
+            EditorTool::Start => "editor.tool.start",
+            EditorTool::Portal => "editor.tool.portal",
+            EditorTool::FoodZone => "editor.tool.food_zone",
+            EditorTool::Erase => "editor.tool.erase",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum EditorAction {
+    SelectTool(EditorTool),
+    TestPlay,
+    Save,
+    Load,
+    Clear,
+}
+
+impl EditorAction {
+    fn label(&self) -> &'static str {
+        match *self {
+            EditorAction::SelectTool(tool) => tool.label(),
+            EditorAction::TestPlay => "editor.test",
+            EditorAction::Save => "editor.save",
+            EditorAction::Load => "editor.load",
+            EditorAction::Clear => "editor.clear",
+        }
+    }
+}
+
+// 正在编辑的关卡，离开编辑器后保留，试玩结束可以继续编辑
+#[derive(Resource)]
+pub struct Editor {
+    level: Level,
+    tool: EditorTool,
+    // 已经放好一端的传送门
+    pending_portal: Option<Point>,
+    // 状态栏显示的提示，字符串表中的 key
+    message: &'static str,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            level: Level::default(),
+            tool: EditorTool::Wall,
+            pending_portal: None,
+            message: "editor.help",
+        }
+    }
+}
+
+#[derive(Component, Default)]
+pub struct OnEditorScreen;
+
+// 编辑中的关卡内容，关卡改变时重新生成
+#[derive(Component)]
+pub struct EditorTile;
+
+#[derive(Component)]
+pub struct EditorStatus;
+
+pub fn setup_editor(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    fonts: Res<UiFonts>,
+    mut editor: ResMut<Editor>,
+) {
+    editor.pending_portal = None;
+    editor.set_changed();
+    let translator = Translator {
+        locale: &locale,
+        fonts: &fonts,
+        asset_server: &asset_server,
+    };
+    let text_style = TextStyle {
+        font: fonts.bold.clone(),
+        font_size: 16.0,
+        color: theme.button_text,
+    };
+
+    // 工具栏盖在记分板上面
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        top: Val::Px(0.0),
+                        left: Val::Px(0.0),
+                        ..default()
+                    },
+                    size: Size::new(Val::Percent(100.0), Val::Px(HUD_HEIGHT)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: theme.menu_background.into(),
+                z_index: ZIndex::Global(1),
+                ..default()
+            },
+            MenuPanel,
+            OnEditorScreen,
+        ))
+        .with_children(|parent| {
+            let actions = EditorTool::ALL
+                .into_iter()
+                .map(EditorAction::SelectTool)
+                .chain([
+                    EditorAction::TestPlay,
+                    EditorAction::Save,
+                    EditorAction::Load,
+                    EditorAction::Clear,
+                ])
+                .map(|action| (action.label(), MenuButtonAction::Editor(action)))
+                .chain([("menu.back", MenuButtonAction::BackToMainMenu)]);
+            for (label, action) in actions {
+                spawn_button(parent, &theme, TOOL_BUTTON_WIDTH, action).with_children(|parent| {
+                    parent.spawn((translator.text(label, &text_style), ThemedText::Button));
+                });
+            }
+        });
+
+    // 状态栏：当前工具和提示
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        bottom: Val::Px(10.0),
+                        left: Val::Px(10.0),
+                        ..default()
+                    },
+                    padding: UiRect::all(Val::Px(6.0)),
+                    ..default()
+                },
+                background_color: theme.menu_background.into(),
+                z_index: ZIndex::Global(1),
+                ..default()
+            },
+            MenuPanel,
+            OnEditorScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_sections([
+                    translator.section(editor.tool.label(), &text_style),
+                    TextSection::new(" | ", text_style.clone()),
+                    translator.section(editor.message, &text_style),
+                ]),
+                ThemedText::Button,
+                EditorStatus,
+            ));
+        });
+}
+
+pub fn handle_editor_action(
+    mut actions: EventReader<MenuActionEvent>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut editor: ResMut<Editor>,
+    mut custom: ResMut<CustomLevel>,
+    mut run: ResMut<CurrentRun>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        app_state.transition(AppState::MainMenu);
+        return;
+    }
+    for MenuActionEvent(action) in actions.iter() {
+        let MenuButtonAction::Editor(action) = action else {
+            continue;
+        };
+        match action {
+            EditorAction::SelectTool(tool) => {
+                editor.tool = *tool;
+                editor.pending_portal = None;
+                editor.message = "editor.help";
+            }
+            EditorAction::TestPlay => match editor.level.validate() {
+                Ok(()) => {
+                    info!("test playing the edited level");
+                    custom.0 = editor.level.clone();
+                    run.mode = GameMode::Custom;
+                    app_state.transition(AppState::InGame(PlayState::Starting));
+                    return;
+                }
+                Err(error) => editor.message = error,
+            },
+            EditorAction::Save => match editor.level.validate() {
+                Ok(()) => {
+                    storage::save(LEVEL_FILE, &editor.level);
+                    editor.message = "editor.saved";
+                }
+                Err(error) => editor.message = error,
+            },
+            EditorAction::Load => match storage::try_load::<Level>(LEVEL_FILE) {
+                Some(level) => {
+                    editor.level = level;
+                    editor.pending_portal = None;
+                    editor.message = "editor.loaded";
+                }
+                None => editor.message = "editor.load_failed",
+            },
+            EditorAction::Clear => {
+                editor.level = Level::default();
+                editor.pending_portal = None;
+                editor.message = "editor.help";
+            }
+        }
+    }
+}
+
+// 鼠标所在的格子，在工具栏上或者棋盘外时为 None
+fn cursor_cell(window: &Window) -> Option<Point> {
+    let cursor = window.cursor_position()?;
+    if cursor.y > window.height() - HUD_HEIGHT {
+        return None;
+    }
+    let world =
+        cursor - Vec2::new(window.width(), window.height()) / 2.0 + CAMERA_OFFSET.truncate();
+    let cell = (world / SQUARE_SIZE).round();
+    let point = Point::new(cell.x as i32, cell.y as i32);
+    point.in_bounds().then_some(point)
+}
+
+pub fn paint_level(
+    windows: Res<Windows>,
+    mouse_input: Res<Input<MouseButton>>,
+    button_query: Query<&Interaction, With<Button>>,
+    mut editor: ResMut<Editor>,
+) {
+    let Some(point) = windows.get_primary().and_then(cursor_cell) else {
+        return;
+    };
+    // 鼠标在按钮上时不画
+    if button_query
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let tool = if mouse_input.pressed(MouseButton::Right) {
+        EditorTool::Erase
+    } else if mouse_input.pressed(MouseButton::Left) {
+        editor.tool
+    } else {
+        return;
+    };
+    let just_pressed = mouse_input.any_just_pressed([MouseButton::Left, MouseButton::Right]);
+
+    // 只在确实有改动时修改，避免每帧重画
+    let level = &editor.level;
+    let is_wall = level.walls.contains(&point);
+    let is_zone = level.food_zones.contains(&point);
+    let portal = level
+        .portals
+        .iter()
+        .position(|(a, b)| *a == point || *b == point);
+    match tool {
+        EditorTool::Wall => {
+            if !is_wall && portal.is_none() {
+                let level = &mut editor.level;
+                level.walls.push(point);
+                level.food_zones.retain(|zone| *zone != point);
+            }
+        }
+        EditorTool::FoodZone => {
+            if !is_zone && !is_wall && portal.is_none() {
+                editor.level.food_zones.push(point);
+            }
+        }
+        EditorTool::Erase => {
+            if is_wall || is_zone || portal.is_some() {
+                let level = &mut editor.level;
+                level.walls.retain(|wall| *wall != point);
+                level.food_zones.retain(|zone| *zone != point);
+                if let Some(index) = portal {
+                    level.portals.remove(index);
+                }
+            }
+            if editor.pending_portal == Some(point) {
+                editor.pending_portal = None;
+            }
+        }
+        // 点击起点时旋转初始方向
+        EditorTool::Start if just_pressed => {
+            let level = &mut editor.level;
+            if level.start == point {
+                level.direction = level.direction.turn_left();
+            } else {
+                level.start = point;
+            }
+        }
+        EditorTool::Portal if just_pressed => {
+            if is_wall || portal.is_some() {
+                return;
+            }
+            match editor.pending_portal {
+                None => editor.pending_portal = Some(point),
+                Some(first) if first != point => {
+                    editor.level.portals.push((first, point));
+                    editor.pending_portal = None;
+                }
+                Some(_) => {}
+            }
+        }
+        EditorTool::Start | EditorTool::Portal => {}
+    }
+}
+
+fn spawn_tile(commands: &mut Commands, point: Point, color: Color, size: f32) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(size)),
+                    ..default()
+                },
+                transform: Transform::from_translation(point.translation()),
+                ..default()
+            },
+            EditorTile,
+            OnEditorScreen,
+        ))
+        .id()
+}
+
+// 关卡改变时重画，并更新状态栏
+pub fn draw_editor(
+    mut commands: Commands,
+    editor: Res<Editor>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    fonts: Res<UiFonts>,
+    asset_server: Res<AssetServer>,
+    tile_query: Query<Entity, With<EditorTile>>,
+    mut status_query: Query<&mut Text, With<EditorStatus>>,
+) {
+    if !editor.is_changed() && !theme.is_changed() && !locale.is_changed() {
+        return;
+    }
+    for entity in &tile_query {
+        commands.entity(entity).despawn();
+    }
+    let level = &editor.level;
+    for point in &level.food_zones {
+        let mut color = theme.food;
+        color.set_a(0.35);
+        spawn_tile(&mut commands, *point, color, SQUARE_SIZE * 0.9);
+    }
+    for point in &level.walls {
+        spawn_tile(&mut commands, *point, theme.border, SQUARE_SIZE);
+    }
+    for (i, (a, b)) in level.portals.iter().enumerate() {
+        for point in [a, b] {
+            spawn_tile(
+                &mut commands,
+                *point,
+                portal_color(&theme, i),
+                SQUARE_SIZE * 0.8,
+            );
+        }
+    }
+    if let Some(point) = editor.pending_portal {
+        let mut color = portal_color(&theme, level.portals.len());
+        color.set_a(0.5);
+        spawn_tile(&mut commands, point, color, SQUARE_SIZE * 0.8);
+    }
+    // 起点的蛇身，蛇头上的小方块指向初始方向
+    for (i, point) in level.snake_body().into_iter().enumerate() {
+        let color = if i == 0 {
+            theme.snake_head
+        } else {
+            theme.snake_tail
+        };
+        spawn_tile(&mut commands, point, color, SQUARE_SIZE * 0.9);
+    }
+    let arrow = spawn_tile(
+        &mut commands,
+        level.start,
+        theme.background,
+        SQUARE_SIZE * 0.3,
+    );
+    let offset = Vec2::from_angle(level.direction.angle()) * SQUARE_SIZE * 0.25;
+    commands.entity(arrow).insert(Transform::from_translation(
+        level.start.translation() + offset.extend(0.1),
+    ));
+
+    let translator = Translator {
+        locale: &locale,
+        fonts: &fonts,
+        asset_server: &asset_server,
+    };
+    for mut text in &mut status_query {
+        let style = TextStyle {
+            font: fonts.bold.clone(),
+            ..text.sections[0].style.clone()
+        };
+        text.sections[0] = translator.section(editor.tool.label(), &style);
+        text.sections[2] = translator.section(editor.message, &style);
+    }
+}
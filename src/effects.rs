@@ -0,0 +1,183 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use crate::{
+    board::CAMERA_OFFSET,
+    common::{AppState, Transition},
+    settings::Settings,
+    snake::{FoodEatenEvent, Point, SnakeDiedEvent},
+    theme::Theme,
+};
+
+// 吃到食物时的粒子
+#[derive(Component)]
+pub struct Particle {
+    velocity: Vec2,
+    lifetime: Timer,
+}
+
+// 死亡时四散的蛇身
+#[derive(Component)]
+pub struct Scatter {
+    velocity: Vec2,
+    spin: f32,
+}
+
+// 新生成食物的弹出动画
+#[derive(Component)]
+pub struct PopIn(pub Timer);
+
+#[derive(Resource, Default)]
+pub struct ScreenShake {
+    timer: Timer,
+    strength: f32,
+}
+
+// 死亡动画结束后才进入游戏结束菜单
+#[derive(Resource)]
+pub struct DeathTimer(Timer);
+
+const PARTICLE_COUNT: usize = 12;
+
+pub fn spawn_food_particles(
+    mut commands: Commands,
+    mut events: EventReader<FoodEatenEvent>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+) {
+    for event in events.iter() {
+        if !settings.effects {
+            continue;
+        }
+        let mut rng = thread_rng();
+        for _ in 0..PARTICLE_COUNT {
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let speed = rng.gen_range(60.0..160.0);
+            commands.spawn((
+                SpriteBundle {
+                    transform: Transform::from_translation(event.point.translation() + Vec3::Z),
+                    sprite: Sprite {
+                        color: event.kind.color(&theme),
+                        custom_size: Some(Vec2::new(4.0, 4.0)),
+                        ..default()
+                    },
+                    ..default()
+                },
+                Particle {
+                    velocity: Vec2::from_angle(angle) * speed,
+                    lifetime: Timer::from_seconds(rng.gen_range(0.3..0.5), TimerMode::Once),
+                },
+            ));
+        }
+    }
+}
+
+pub fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    for (entity, mut particle, mut transform, mut sprite) in &mut query {
+        if particle.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.0);
+        sprite.color.set_a(1.0 - particle.lifetime.percent());
+    }
+}
+
+pub fn pop_in_food(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut PopIn, &mut Transform)>,
+) {
+    for (entity, mut pop_in, mut transform) in &mut query {
+        let t = pop_in.0.tick(time.delta()).percent();
+        // 先放大超过原尺寸再回弹
+        let c = 1.70158;
+        let scale = 1.0 + (c + 1.0) * (t - 1.0).powi(3) + c * (t - 1.0).powi(2);
+        transform.scale = Vec3::splat(scale);
+        if pop_in.0.finished() {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).remove::<PopIn>();
+        }
+    }
+}
+
+pub fn start_death_animation(
+    mut commands: Commands,
+    mut events: EventReader<SnakeDiedEvent>,
+    segment_query: Query<(Entity, &Transform), With<Point>>,
+    mut shake: ResMut<ScreenShake>,
+    settings: Res<Settings>,
+) {
+    // 蛇身从撞击的位置向外飞散
+    let origin = events
+        .iter()
+        .last()
+        .map(|event| event.point.translation().truncate())
+        .unwrap_or_default();
+    if !settings.effects {
+        commands.insert_resource(DeathTimer(Timer::from_seconds(0.0, TimerMode::Once)));
+        return;
+    }
+
+    let mut rng = thread_rng();
+    for (entity, transform) in &segment_query {
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        let away = (transform.translation.truncate() - origin).normalize_or_zero();
+        commands.entity(entity).insert(Scatter {
+            velocity: (away + Vec2::from_angle(angle) * 0.5) * rng.gen_range(80.0..240.0),
+            spin: rng.gen_range(-8.0..8.0),
+        });
+    }
+    *shake = ScreenShake {
+        timer: Timer::from_seconds(0.35, TimerMode::Once),
+        strength: 8.0,
+    };
+    commands.insert_resource(DeathTimer(Timer::from_seconds(0.9, TimerMode::Once)));
+}
+
+pub fn finish_death_animation(
+    time: Res<Time>,
+    mut timer: ResMut<DeathTimer>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    if timer.0.tick(time.delta()).finished() {
+        app_state.transition(AppState::GameOver);
+    }
+}
+
+pub fn scatter_segments(
+    time: Res<Time>,
+    mut query: Query<(&Scatter, &mut Transform, &mut TextureAtlasSprite)>,
+) {
+    for (scatter, mut transform, mut sprite) in &mut query {
+        transform.translation += (scatter.velocity * time.delta_seconds()).extend(0.0);
+        transform.rotate_z(scatter.spin * time.delta_seconds());
+        let alpha = sprite.color.a();
+        sprite.color.set_a((alpha - time.delta_seconds()).max(0.0));
+    }
+}
+
+pub fn shake_camera(
+    time: Res<Time>,
+    mut shake: ResMut<ScreenShake>,
+    mut query: Query<&mut Transform, With<Camera>>,
+) {
+    if shake.timer.finished() {
+        return;
+    }
+    let strength = shake.strength * (1.0 - shake.timer.tick(time.delta()).percent());
+    let offset = if shake.timer.finished() {
+        Vec2::ZERO
+    } else {
+        let mut rng = thread_rng();
+        Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)) * strength
+    };
+    for mut transform in &mut query {
+        transform.translation.x = CAMERA_OFFSET.x + offset.x;
+        transform.translation.y = CAMERA_OFFSET.y + offset.y;
+    }
+}
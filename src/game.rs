@@ -0,0 +1,303 @@
+use bevy::{prelude::*, transform::TransformSystem};
+
+use crate::{
+    achievements::*, board::*, campaign::*, clock::*, common::*, editor::*, effects::*, ghost::*,
+    hazard::*, leaderboard::*, level::*, locale::*, menu::*, mode::*, replay::*, rewind::*,
+    ron_asset::*, savegame::*, score::*, settings::*, shrink::*, snake::*, theme::*,
+    time_attack::*,
+};
+
+// 游戏的全部资源、状态和系统，不包括窗口、渲染和音频等引擎插件
+// main 中和 DefaultPlugins 一起使用，测试中和 MinimalPlugins 一起使用
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Score(0))
+            .insert_resource(Record(0))
+            .init_resource::<RunStats>()
+            .init_resource::<CurrentRun>()
+            .init_resource::<Level>()
+            .init_resource::<CustomLevel>()
+            .init_resource::<Editor>()
+            .init_resource::<Campaign>()
+            .init_resource::<NextSeed>()
+            .init_resource::<Invincible>()
+            .init_resource::<RewindBuffer>()
+            .init_resource::<RunRecorder>()
+            .init_resource::<Countdown>()
+            .init_resource::<Arena>()
+            .init_resource::<GameClock>()
+            .init_resource::<Achievements>()
+            .init_resource::<AchievementTracker>()
+            .init_resource::<ScreenShake>()
+            .init_resource::<Theme>()
+            .init_resource::<Locale>()
+            .add_event::<FoodEatenEvent>()
+            .add_event::<SnakeMovedEvent>()
+            .add_event::<SnakeDiedEvent>()
+            .add_event::<AchievementUnlockedEvent>()
+            .add_event::<MenuActionEvent>()
+            .init_resource::<MenuFocus>()
+            .add_state(AppState::MainMenu)
+            .add_asset::<Theme>()
+            .add_asset_loader(RonAssetLoader::<Theme>::new(&["theme.ron"]))
+            .add_startup_system(setup_themes)
+            .add_asset::<LocaleTable>()
+            .add_asset_loader(RonAssetLoader::<LocaleTable>::new(&["locale.ron"]))
+            .init_resource::<UiFonts>()
+            .add_startup_system(setup_locales)
+            .add_startup_system(setup)
+            .add_startup_system_to_stage(StartupStage::PreStartup, setup_settings)
+            .add_startup_system_to_stage(StartupStage::PreStartup, setup_hud_row)
+            .add_startup_system(setup_score)
+            .add_startup_system(setup_timeboard)
+            .add_startup_system(setup_rewind_hint)
+            .add_startup_system(setup_high_scores)
+            .add_startup_system(setup_daily_results)
+            .add_startup_system(setup_campaign)
+            .add_startup_system(setup_replays)
+            .add_startup_system(setup_leaderboard)
+            .add_startup_system(setup_achievements)
+            .add_startup_system(setup_game_audios)
+            .add_startup_system(setup_snake_atlas)
+            .add_startup_system(setup_food_assets)
+            .add_startup_system(setup_board)
+            .add_system_set(
+                SystemSet::on_enter(AppState::MainMenu)
+                    .with_system(main_menu.pipe(spawn_menu::<OnMainMenuScreen>))
+                    .with_system(clear_snake)
+                    .with_system(clear_ghost)
+                    .with_system(clear_food)
+                    .with_system(clear_score)
+                    .with_system(reset_achievement_tracker)
+                    .with_system(reset_arena)
+                    .with_system(reset_game_clock)
+                    .with_system(setup_level.before(setup_snake))
+                    .with_system(setup_snake),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::MainMenu)
+                    .with_system(despawn_screen::<OnMainMenuScreen>),
+            )
+            // Settings Menu
+            .add_system_set(
+                SystemSet::on_enter(AppState::Settings)
+                    .with_system(settings_menu.pipe(spawn_menu::<OnSettingsMenuScreen>)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Settings)
+                    .with_system(despawn_screen::<OnSettingsMenuScreen>),
+            )
+            // Mode Select Menu
+            .add_system_set(
+                SystemSet::on_enter(AppState::ModeSelect)
+                    .with_system(mode_menu.pipe(spawn_menu::<OnModeSelectMenuScreen>)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::ModeSelect)
+                    .with_system(despawn_screen::<OnModeSelectMenuScreen>),
+            )
+            // Level Select Menu
+            .add_system_set(
+                SystemSet::on_enter(AppState::LevelSelect)
+                    .with_system(level_select_menu.pipe(spawn_menu::<OnLevelSelectMenuScreen>)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::LevelSelect)
+                    .with_system(despawn_screen::<OnLevelSelectMenuScreen>),
+            )
+            // Achievements Menu
+            .add_system_set(
+                SystemSet::on_enter(AppState::Achievements)
+                    .with_system(achievements_menu.pipe(spawn_menu::<OnAchievementsMenuScreen>)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Achievements)
+                    .with_system(despawn_screen::<OnAchievementsMenuScreen>),
+            )
+            // High Scores Menu
+            .add_system_set(
+                SystemSet::on_enter(AppState::HighScores)
+                    .with_system(high_scores_menu.pipe(spawn_menu::<OnHighScoresMenuScreen>)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::HighScores)
+                    .with_system(despawn_screen::<OnHighScoresMenuScreen>),
+            )
+            // Level Editor
+            .add_system_set(
+                SystemSet::on_enter(AppState::Editor)
+                    .with_system(clear_snake)
+                    .with_system(clear_ghost)
+                    .with_system(clear_food)
+                    .with_system(clear_level)
+                    .with_system(setup_editor),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Editor)
+                    .with_system(handle_editor_action.after(handle_menu_action))
+                    .with_system(paint_level.before(draw_editor))
+                    .with_system(draw_editor.after(handle_editor_action)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Editor).with_system(despawn_screen::<OnEditorScreen>),
+            )
+            // Pause Menu
+            .add_system(pause_game)
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame(PlayState::Paused))
+                    .with_system(pause_menu.pipe(spawn_menu::<OnGamePausedMenuScreen>))
+                    .with_system(save_game),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::InGame(PlayState::Paused))
+                    .with_system(despawn_screen::<OnGamePausedMenuScreen>),
+            )
+            // Dying
+            .add_system_set(
+                SystemSet::on_enter(AppState::Dying)
+                    .with_system(start_death_animation)
+                    .with_system(record_run)
+                    .with_system(save_achievements)
+                    .with_system(save_replay)
+                    .with_system(queue_score)
+                    .with_system(delete_saved_game),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Dying).with_system(finish_death_animation),
+            )
+            // Game Over Menu
+            .add_system_set(
+                SystemSet::on_enter(AppState::GameOver)
+                    .with_system(game_over_menu.pipe(spawn_menu::<OnGameOverMenuScreen>)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::GameOver)
+                    .with_system(despawn_screen::<OnGameOverMenuScreen>),
+            )
+            // Level Complete Menu
+            .add_system_set(
+                SystemSet::on_enter(AppState::LevelComplete)
+                    .with_system(level_complete_menu.pipe(spawn_menu::<OnLevelCompleteMenuScreen>))
+                    .with_system(save_achievements)
+                    .with_system(save_replay)
+                    .with_system(queue_score)
+                    .with_system(delete_saved_game),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::LevelComplete)
+                    .with_system(despawn_screen::<OnLevelCompleteMenuScreen>),
+            )
+            // Game Playing
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame(PlayState::Running))
+                    .with_system(advance_game_clock)
+                    .with_system(generate_food.after(move_snake).after(move_hazards))
+                    .with_system(move_snake.after(advance_game_clock))
+                    .with_system(contral_snake)
+                    .with_system(update_scoreboard)
+                    .with_system(update_recordboard)
+                    .with_system(track_run_time.after(advance_game_clock))
+                    .with_system(count_food.after(move_snake).before(check_campaign_goal))
+                    .with_system(unlock_achievements)
+                    .with_system(tick_countdown.after(move_snake))
+                    .with_system(shrink_arena.after(move_snake))
+                    .with_system(move_hazards.after(advance_game_clock))
+                    .with_system(hazard_collisions.after(move_snake).after(move_hazards))
+                    .with_system(
+                        check_campaign_goal
+                            .after(hazard_collisions)
+                            .after(track_run_time),
+                    )
+                    .with_system(record_rewind.before(move_snake))
+                    .with_system(start_rewind.after(hazard_collisions))
+                    .with_system(record_steps.after(move_snake))
+                    .with_system(move_ghost.after(advance_game_clock)),
+            )
+            // Game Starting
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame(PlayState::Starting))
+                    .with_system(clear_snake)
+                    .with_system(clear_food)
+                    .with_system(clear_score)
+                    .with_system(reset_achievement_tracker)
+                    .with_system(start_run.before(setup_level).before(show_mode_record))
+                    .with_system(show_mode_record)
+                    .with_system(reset_countdown.after(start_run))
+                    .with_system(reset_arena)
+                    .with_system(reset_game_clock)
+                    .with_system(setup_level.before(setup_snake))
+                    .with_system(setup_snake)
+                    .with_system(delete_saved_game)
+                    .with_system(reset_rewind)
+                    .with_system(reset_recorder)
+                    .with_system(clear_ghost)
+                    .with_system(spawn_ghost.after(start_run).after(setup_level)),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame(PlayState::Starting)).with_system(play_game),
+            )
+            // Rewinding
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame(PlayState::Rewinding))
+                    .with_system(rewind_game),
+            )
+            // Game Resuming
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame(PlayState::Resuming))
+                    .with_system(reset_achievement_tracker)
+                    .with_system(reset_game_clock)
+                    .with_system(reset_rewind)
+                    .with_system(discard_recorder)
+                    .with_system(clear_ghost)
+                    .with_system(reset_arena),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame(PlayState::Resuming))
+                    .with_system(resume_game),
+            )
+            // Effects
+            .add_system(spawn_food_particles)
+            .add_system(update_particles)
+            .add_system(pop_in_food)
+            .add_system(scatter_segments)
+            .add_system(shake_camera)
+            .add_system(toggle_grid)
+            .add_system(update_timeboard)
+            .add_system(update_goalboard)
+            .add_system(update_rewind_hint)
+            .add_system(render_ghost)
+            .add_system(blink_warnings)
+            // Achievements
+            .add_system(track_achievements)
+            .add_system(spawn_achievement_toasts)
+            .add_system(update_achievement_toasts)
+            .add_system(save_on_close)
+            // Leaderboard
+            .add_system(sync_leaderboard)
+            .add_system(handle_leaderboard_action.after(handle_menu_action))
+            // Menu
+            .add_system(click_button.before(handle_menu_action))
+            .add_system(navigate_menu.before(handle_menu_action))
+            .add_system(handle_menu_action)
+            .add_system(button_colors)
+            .add_system(update_menu_widgets)
+            // Theme
+            .add_system(select_theme)
+            .add_system(apply_theme.after(select_theme))
+            // Locale
+            .add_system(select_locale)
+            .add_system(relabel_texts.after(select_locale))
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                render_snake.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+fn setup(mut commands: Commands) {
+    let mut camera = Camera2dBundle::default();
+    camera.transform.translation += CAMERA_OFFSET;
+    commands.spawn(camera);
+}
@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+
+use crate::{
+    clock::GameClock,
+    level::Level,
+    mode::CurrentRun,
+    replay::{Replay, Replays},
+    snake::{Point, MOVE_INTERVAL, SQUARE_SIZE},
+    theme::Theme,
+};
+
+// 个人最好记录的影子，和蛇同时出发，不会碰撞
+#[derive(Component)]
+pub struct Ghost {
+    replay: Replay,
+    timer: Timer,
+    step: usize,
+    // 从蛇头到蛇尾
+    body: Vec<Point>,
+}
+
+#[derive(Component)]
+pub struct GhostSegment;
+
+pub fn spawn_ghost(
+    mut commands: Commands,
+    run: Res<CurrentRun>,
+    replays: Res<Replays>,
+    level: Res<Level>,
+) {
+    let Some(replay) = replays.best(&run) else {
+        return;
+    };
+    commands.spawn((
+        SpatialBundle::default(),
+        Ghost {
+            replay: replay.clone(),
+            timer: Timer::from_seconds(MOVE_INTERVAL, TimerMode::Repeating),
+            step: 0,
+            body: level.snake_body(),
+        },
+    ));
+}
+
+pub fn clear_ghost(mut commands: Commands, query: Query<Entity, With<Ghost>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// 按记录的方向走，记录走完后停在最后的位置
+pub fn move_ghost(clock: Res<GameClock>, level: Res<Level>, mut query: Query<&mut Ghost>) {
+    for mut ghost in &mut query {
+        if !ghost.timer.tick(clock.delta()).just_finished() {
+            continue;
+        }
+        let ghost = &mut *ghost;
+        let Some(direction) = ghost.replay.steps.get(ghost.step) else {
+            continue;
+        };
+        let head = level.step(&ghost.body[0], direction);
+        ghost.body.insert(0, head);
+        if !ghost.replay.eaten.contains(&ghost.step) {
+            ghost.body.pop();
+        }
+        ghost.step += 1;
+    }
+}
+
+// 身体变长时增加方块，位置跟随记录
+pub fn render_ghost(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    ghost_query: Query<(Entity, &Ghost, Option<&Children>)>,
+    mut segment_query: Query<&mut Transform, With<GhostSegment>>,
+) {
+    for (entity, ghost, children) in &ghost_query {
+        let children = children.map_or(&[][..], |children| &children[..]);
+        for (i, point) in ghost.body.iter().enumerate() {
+            // 画在蛇的下面，网格的上面
+            let translation = point.translation() - Vec3::Z * 0.05;
+            match children.get(i) {
+                Some(child) => {
+                    if let Ok(mut transform) = segment_query.get_mut(*child) {
+                        transform.translation = translation;
+                    }
+                }
+                None => {
+                    let mut color = theme.snake_head;
+                    color.set_a(0.3);
+                    let segment = commands
+                        .spawn((
+                            SpriteBundle {
+                                sprite: Sprite {
+                                    color,
+                                    custom_size: Some(Vec2::splat(SQUARE_SIZE * 0.8)),
+                                    ..default()
+                                },
+                                transform: Transform::from_translation(translation),
+                                ..default()
+                            },
+                            GhostSegment,
+                        ))
+                        .id();
+                    commands.entity(entity).add_child(segment);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use rand::prelude::*;
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
+
+use crate::{
+    clock::GameClock,
+    level::Level,
+    snake::{DeathCause, Direction, GameEnd, Point, Snake, SnakeMovedEvent, SQUARE_SIZE},
+    theme::Theme,
+};
+
+// 障碍的移动方式
+#[derive(Clone, Serialize, Deserialize)]
+pub enum HazardMotion {
+    // 依次走向各个路点，走完后回到第一个
+    Patrol(Vec<Point>),
+    // 直线移动，碰到墙掉头
+    Bounce(Direction),
+    // 追着蛇头走
+    Chase,
+}
+
+// 关卡文件中的障碍
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HazardSpec {
+    pub start: Point,
+    pub motion: HazardMotion,
+    // 每走一格的秒数
+    #[serde(default = "default_interval", deserialize_with = "load_interval")]
+    pub interval: f32,
+}
+
+fn default_interval() -> f32 {
+    0.3
+}
+
+// 障碍每走一格最短的间隔，校验时的模拟以毫秒计
+const MIN_INTERVAL: f32 = 0.001;
+
+// 关卡文件和存档中的间隔，0、负数和无穷大会让计时器出错
+fn load_interval<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let interval = f32::deserialize(deserializer)?;
+    if interval >= MIN_INTERVAL && interval.is_finite() {
+        Ok(interval)
+    } else {
+        Err(D::Error::custom(format!(
+            "invalid hazard interval {interval}"
+        )))
+    }
+}
+
+// 会移动的障碍，碰到蛇就会让蛇死掉
+#[derive(Component)]
+pub struct Hazard {
+    pub point: Point,
+    motion: HazardMotion,
+    timer: Timer,
+    waypoint: usize,
+    // 这一帧移动前的格子，没有移动时为 None
+    moved_from: Option<Point>,
+}
+
+// 存档中的障碍：当前位置和移动进度
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HazardState {
+    pub point: Point,
+    pub motion: HazardMotion,
+    pub waypoint: usize,
+    #[serde(deserialize_with = "load_interval")]
+    pub interval: f32,
+    pub elapsed: f32,
+}
+
+impl Hazard {
+    pub fn state(&self) -> HazardState {
+        HazardState {
+            point: self.point,
+            motion: self.motion.clone(),
+            waypoint: self.waypoint,
+            interval: self.timer.duration().as_secs_f32(),
+            elapsed: self.timer.elapsed_secs(),
+        }
+    }
+
+    // 距离下一次移动还有多久
+    pub fn remaining(&self) -> Duration {
+        self.timer.remaining()
+    }
+}
+
+// 随机障碍模式中每种障碍的个数
+const RANDOM_HAZARDS: usize = 1;
+
+impl Level {
+    // 随机放置巡逻、反弹和追踪的障碍各一个，远离蛇的起点，找不到空位的障碍不放
+    pub fn with_random_hazards(mut self, rng: &mut impl Rng) -> Self {
+        let body = self.snake_body();
+        let free_point = |rng: &mut _, level: &Level| {
+            for _ in 0..1000 {
+                let point = Point::random(rng);
+                let near_snake = body
+                    .iter()
+                    .any(|other| (other.x - point.x).abs() <= 3 && (other.y - point.y).abs() <= 3);
+                if !near_snake && point.y != level.start.y && !level.is_blocked(&point) {
+                    return Some(point);
+                }
+            }
+            None
+        };
+        for _ in 0..RANDOM_HAZARDS {
+            let corner = free_point(rng, &self);
+            let opposite = free_point(rng, &self);
+            if let (Some(corner), Some(opposite)) = (corner, opposite) {
+                self.hazards.push(HazardSpec {
+                    start: corner,
+                    motion: HazardMotion::Patrol(vec![
+                        corner,
+                        Point::new(opposite.x, corner.y),
+                        opposite,
+                        Point::new(corner.x, opposite.y),
+                    ]),
+                    interval: 0.3,
+                });
+            }
+            let direction = if rng.gen_bool(0.5) {
+                Direction::Up
+            } else {
+                Direction::Right
+            };
+            if let Some(start) = free_point(rng, &self) {
+                self.hazards.push(HazardSpec {
+                    start,
+                    motion: HazardMotion::Bounce(direction),
+                    interval: 0.25,
+                });
+            }
+            if let Some(start) = free_point(rng, &self) {
+                self.hazards.push(HazardSpec {
+                    start,
+                    motion: HazardMotion::Chase,
+                    interval: 0.5,
+                });
+            }
+        }
+        self
+    }
+
+    // 开局时的障碍，不能出生在蛇身上或者墙里
+    pub fn initial_hazards(&self) -> Vec<HazardState> {
+        let body = self.snake_body();
+        let mut hazards = Vec::new();
+        for spec in &self.hazards {
+            if body.contains(&spec.start) || self.is_blocked(&spec.start) {
+                warn!(
+                    "hazard at {} overlaps the snake or a wall, skipped",
+                    spec.start
+                );
+                continue;
+            }
+            hazards.push(HazardState {
+                point: spec.start,
+                motion: spec.motion.clone(),
+                waypoint: 0,
+                interval: spec.interval,
+                elapsed: 0.0,
+            });
+        }
+        hazards
+    }
+}
+
+pub fn spawn_hazards(commands: &mut Commands, theme: &Theme, level: &Level) {
+    for state in level.initial_hazards() {
+        spawn_hazard(commands, theme, state);
+    }
+}
+
+pub fn spawn_hazard(commands: &mut Commands, theme: &Theme, state: HazardState) {
+    let mut timer = Timer::from_seconds(state.interval, TimerMode::Repeating);
+    timer.set_elapsed(Duration::from_secs_f32(state.elapsed));
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: theme.hazard,
+                custom_size: Some(Vec2::splat(SQUARE_SIZE * 0.7)),
+                ..default()
+            },
+            transform: Transform::from_translation(state.point.translation()),
+            ..default()
+        },
+        Hazard {
+            point: state.point,
+            motion: state.motion,
+            timer,
+            waypoint: state.waypoint,
+            moved_from: None,
+        },
+    ));
+}
+
+// 从 from 走一步靠近 to，优先走距离远的方向
+fn step_towards(level: &Level, from: Point, to: Point) -> Point {
+    let dx = to.x - from.x;
+    let dy = to.y - from.y;
+    let horizontal = Point::new(from.x + dx.signum(), from.y);
+    let vertical = Point::new(from.x, from.y + dy.signum());
+    let candidates = if dx.abs() >= dy.abs() {
+        [horizontal, vertical]
+    } else {
+        [vertical, horizontal]
+    };
+    candidates
+        .into_iter()
+        .find(|point| *point != from && !level.is_blocked(point))
+        .unwrap_or(from)
+}
+
+// 障碍走一步后的位置，追踪的障碍走向 head
+pub fn next_hazard_point(
+    level: &Level,
+    point: Point,
+    motion: &mut HazardMotion,
+    waypoint: &mut usize,
+    head: Option<Point>,
+) -> Point {
+    match motion {
+        HazardMotion::Patrol(waypoints) => {
+            let Some(&target) = waypoints.get(*waypoint) else {
+                return point;
+            };
+            if point == target {
+                *waypoint = (*waypoint + 1) % waypoints.len();
+            }
+            step_towards(level, point, waypoints[*waypoint])
+        }
+        HazardMotion::Bounce(direction) => {
+            let next = point.next(direction);
+            if level.is_blocked(&next) {
+                *direction = direction.opposite();
+                let back = point.next(direction);
+                if level.is_blocked(&back) {
+                    point
+                } else {
+                    back
+                }
+            } else {
+                next
+            }
+        }
+        HazardMotion::Chase => match head {
+            Some(head) => step_towards(level, point, head),
+            None => point,
+        },
+    }
+}
+
+pub fn move_hazards(
+    clock: Res<GameClock>,
+    level: Res<Level>,
+    snake_query: Query<&Children, With<Snake>>,
+    point_query: Query<&Point>,
+    mut hazard_query: Query<(&mut Hazard, &mut Transform)>,
+) {
+    let head = snake_query
+        .iter()
+        .find_map(|children| point_query.get(children[0]).ok().copied());
+    for (mut hazard, mut transform) in &mut hazard_query {
+        hazard.moved_from = None;
+        if !hazard.timer.tick(clock.delta()).just_finished() {
+            continue;
+        }
+        let hazard = &mut *hazard;
+        hazard.moved_from = Some(hazard.point);
+        hazard.point = next_hazard_point(
+            &level,
+            hazard.point,
+            &mut hazard.motion,
+            &mut hazard.waypoint,
+            head,
+        );
+        transform.translation = hazard.point.translation();
+    }
+}
+
+// 障碍和蛇身任何一节重叠，或者和蛇头互换了位置都会死
+pub fn hazard_collisions(
+    mut moved_events: EventReader<SnakeMovedEvent>,
+    hazard_query: Query<&Hazard>,
+    snake_query: Query<&Children, With<Snake>>,
+    point_query: Query<&Point>,
+    mut game_end: GameEnd,
+) {
+    // 这一帧蛇头移动前后的格子，吃到食物时新的蛇头还没有生成
+    let moves: Vec<(Point, Point)> = moved_events
+        .iter()
+        .map(|event| (event.from, event.to))
+        .collect();
+    for hazard in &hazard_query {
+        let hit = snake_query
+            .iter()
+            .flat_map(|children| children.iter())
+            .filter_map(|entity| point_query.get(*entity).ok())
+            .any(|point| *point == hazard.point)
+            || moves.iter().any(|(from, to)| {
+                *to == hazard.point || (hazard.moved_from == Some(*to) && hazard.point == *from)
+            });
+        if hit {
+            game_end.die(hazard.point, DeathCause::Hazard);
+            return;
+        }
+    }
+}
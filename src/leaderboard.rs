@@ -0,0 +1,419 @@
+use std::{
+    fmt,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    campaign::Campaign,
+    common::AppState,
+    menu::{MenuActionEvent, MenuButtonAction, MenuDescription, MenuItem},
+    mode::{daily_table, today, CurrentRun, GameMode},
+    replay::{Replay, RunRecorder},
+    score::{HighScores, Score},
+    storage,
+    time_attack::TIME_ATTACK_LIMITS,
+};
+
+const CONFIG_FILE: &str = "leaderboard.ron";
+// 还没有提交成功的成绩，下次启动后继续提交
+const QUEUE_FILE: &str = "leaderboard_queue.ron";
+// 全球排行榜读取的名次数
+pub const GLOBAL_LIMIT: usize = 50;
+// 排行榜界面每列显示的名次数
+const COLUMN_ROWS: usize = 10;
+// 连不上服务器时，过一段时间再重试
+const RETRY_INTERVAL: f32 = 30.0;
+const REQUEST_TIMEOUT: u64 = 5;
+
+// 排行榜服务器设置，保存在数据目录，没有设置服务器时只记录本地成绩
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct LeaderboardConfig {
+    // 例如 "http://localhost:8080"
+    pub server: Option<String>,
+    pub player: String,
+}
+
+impl Default for LeaderboardConfig {
+    fn default() -> Self {
+        Self {
+            server: None,
+            player: "Player".to_string(),
+        }
+    }
+}
+
+// 提交的一局，模式、种子和分数都在操作记录中
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Submission {
+    pub player: String,
+    pub replay: Replay,
+}
+
+// 全球排行榜中的一个名次
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub player: String,
+    pub score: u32,
+}
+
+#[derive(Debug)]
+pub enum RequestError {
+    // 连不上服务器，稍后重试
+    Offline(String),
+    // 服务器拒绝了请求，重试也没有用
+    Rejected(u16, String),
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestError::Offline(reason) => write!(f, "server unreachable: {reason}"),
+            RequestError::Rejected(status, reason) => write!(f, "rejected ({status}): {reason}"),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<ureq::Error> for RequestError {
+    fn from(err: ureq::Error) -> Self {
+        match err {
+            ureq::Error::Status(status, response) => {
+                RequestError::Rejected(status, response.into_string().unwrap_or_default())
+            }
+            ureq::Error::Transport(transport) => RequestError::Offline(transport.to_string()),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT))
+        .build()
+}
+
+// POST <server>/scores
+#[cfg(not(target_arch = "wasm32"))]
+pub fn submit(server: &str, submission: &Submission) -> Result<(), RequestError> {
+    agent()
+        .post(&format!("{}/scores", server.trim_end_matches('/')))
+        .send_json(submission)?;
+    Ok(())
+}
+
+// GET <server>/scores/<table>?limit=<limit>，从高到低
+#[cfg(not(target_arch = "wasm32"))]
+pub fn fetch_scores(
+    server: &str,
+    table: &str,
+    limit: usize,
+) -> Result<Vec<ScoreEntry>, RequestError> {
+    let url = format!(
+        "{}/scores/{}?limit={}",
+        server.trim_end_matches('/'),
+        table,
+        limit
+    );
+    agent()
+        .get(&url)
+        .call()?
+        .into_json()
+        .map_err(|err| RequestError::Offline(err.to_string()))
+}
+
+// 网页版不能直接访问排行榜服务器
+#[cfg(target_arch = "wasm32")]
+pub fn submit(_server: &str, _submission: &Submission) -> Result<(), RequestError> {
+    Err(RequestError::Offline(
+        "not supported in the browser".to_string(),
+    ))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn fetch_scores(
+    _server: &str,
+    _table: &str,
+    _limit: usize,
+) -> Result<Vec<ScoreEntry>, RequestError> {
+    Err(RequestError::Offline(
+        "not supported in the browser".to_string(),
+    ))
+}
+
+// 后台请求的结果
+enum Reply {
+    // 从队列开头依次处理完的提交数，之后的连不上服务器
+    Submitted(usize),
+    Scores(String, Result<Vec<ScoreEntry>, RequestError>),
+}
+
+// 网络请求在后台线程中进行，不卡住游戏
+#[cfg(not(target_arch = "wasm32"))]
+fn request(sender: &Sender<Reply>, task: impl FnOnce() -> Reply + Send + 'static) {
+    let sender = sender.clone();
+    std::thread::spawn(move || {
+        // 游戏已经退出时没有人接收
+        let _ = sender.send(task());
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+fn request(sender: &Sender<Reply>, task: impl FnOnce() -> Reply) {
+    let _ = sender.send(task());
+}
+
+// 排行榜界面显示的全球排行
+enum GlobalScores {
+    Disabled,
+    Loading,
+    Loaded(Vec<ScoreEntry>),
+    Offline,
+}
+
+#[derive(Resource)]
+pub struct Leaderboard {
+    config: LeaderboardConfig,
+    queue: Vec<Submission>,
+    // 距离下一次提交的秒数
+    retry_in: f32,
+    sending: bool,
+    // 排行榜界面当前显示的模式
+    view: GameMode,
+    global: GlobalScores,
+    sender: Sender<Reply>,
+    receiver: Mutex<Receiver<Reply>>,
+}
+
+impl Leaderboard {
+    // 全球排行榜名称，每日挑战显示当天的
+    fn view_table(&self) -> String {
+        match self.view {
+            GameMode::Daily => daily_table(&today()),
+            mode => mode.table(),
+        }
+    }
+
+    // 读取当前显示模式的全球排行
+    fn fetch(&mut self) {
+        let Some(server) = self.config.server.clone() else {
+            self.global = GlobalScores::Disabled;
+            return;
+        };
+        self.global = GlobalScores::Loading;
+        let table = self.view_table();
+        request(&self.sender, move || {
+            let result = fetch_scores(&server, &table, GLOBAL_LIMIT);
+            Reply::Scores(table, result)
+        });
+    }
+}
+
+pub fn setup_leaderboard(mut commands: Commands) {
+    let (sender, receiver) = mpsc::channel();
+    commands.insert_resource(Leaderboard {
+        config: storage::load(CONFIG_FILE),
+        queue: storage::load(QUEUE_FILE),
+        retry_in: 0.0,
+        sending: false,
+        view: GameMode::Classic,
+        global: GlobalScores::Disabled,
+        sender,
+        receiver: Mutex::new(receiver),
+    });
+}
+
+// 一局结束时，计入排行榜的成绩加入提交队列
+pub fn queue_score(
+    run: Res<CurrentRun>,
+    score: Res<Score>,
+    recorder: Res<RunRecorder>,
+    mut leaderboard: ResMut<Leaderboard>,
+) {
+    if leaderboard.config.server.is_none() || !run.counted || score.0 == 0 {
+        return;
+    }
+    // 继续保存的一局没有完整的操作记录，无法重现
+    let Some(replay) = recorder.replay(&run, score.0) else {
+        return;
+    };
+    let player = leaderboard.config.player.clone();
+    leaderboard.queue.push(Submission { player, replay });
+    storage::save(QUEUE_FILE, &leaderboard.queue);
+    leaderboard.retry_in = 0.0;
+}
+
+// 在后台依次提交队列中的成绩，处理服务器的回复
+pub fn sync_leaderboard(
+    time: Res<Time>,
+    mut app_state: ResMut<State<AppState>>,
+    mut leaderboard: ResMut<Leaderboard>,
+) {
+    let replies: Vec<Reply> = leaderboard
+        .receiver
+        .lock()
+        .map(|receiver| receiver.try_iter().collect())
+        .unwrap_or_default();
+    for reply in replies {
+        match reply {
+            Reply::Submitted(handled) => {
+                leaderboard.sending = false;
+                leaderboard.queue.drain(..handled);
+                storage::save(QUEUE_FILE, &leaderboard.queue);
+                if !leaderboard.queue.is_empty() {
+                    leaderboard.retry_in = RETRY_INTERVAL;
+                }
+            }
+            Reply::Scores(table, result) => {
+                // 已经切换到别的模式
+                if table != leaderboard.view_table() {
+                    continue;
+                }
+                leaderboard.global = match result {
+                    Ok(entries) => GlobalScores::Loaded(entries),
+                    Err(err) => {
+                        warn!("failed to fetch leaderboard {}: {}", table, err);
+                        GlobalScores::Offline
+                    }
+                };
+                // 重新生成排行榜界面
+                if *app_state.current() == AppState::HighScores {
+                    let _ = app_state.restart();
+                }
+            }
+        }
+    }
+
+    let Some(server) = leaderboard.config.server.clone() else {
+        return;
+    };
+    if leaderboard.sending || leaderboard.queue.is_empty() {
+        return;
+    }
+    leaderboard.retry_in -= time.delta_seconds();
+    if leaderboard.retry_in > 0.0 {
+        return;
+    }
+    leaderboard.sending = true;
+    let queue = leaderboard.queue.clone();
+    request(&leaderboard.sender, move || {
+        let mut handled = 0;
+        for submission in &queue {
+            match submit(&server, submission) {
+                Ok(()) => {}
+                Err(RequestError::Offline(reason)) => {
+                    warn!("failed to submit score, will retry: {}", reason);
+                    break;
+                }
+                Err(err) => warn!("score submission dropped: {}", err),
+            }
+            handled += 1;
+        }
+        Reply::Submitted(handled)
+    });
+}
+
+// 有排行榜的模式，在排行榜界面依次切换
+fn ranked_modes(campaign: &Campaign) -> Vec<GameMode> {
+    let mut modes = vec![GameMode::Classic, GameMode::Daily];
+    modes.extend(TIME_ATTACK_LIMITS.map(GameMode::TimeAttack));
+    modes.extend([GameMode::Shrinking, GameMode::Portals, GameMode::Hazards]);
+    modes.extend(
+        (0..)
+            .take_while(|&index| campaign.level(index).is_some())
+            .map(GameMode::Campaign),
+    );
+    modes
+}
+
+// 字符串表中的 key，限时模式和闯关的每一关分开显示
+fn mode_name(mode: GameMode, campaign: &Campaign) -> String {
+    match mode {
+        GameMode::TimeAttack(seconds) => format!("menu.time_attack_{seconds}"),
+        GameMode::Campaign(index) => campaign
+            .level(index)
+            .map_or_else(|| mode.label().to_string(), |level| level.name()),
+        _ => mode.label().to_string(),
+    }
+}
+
+// 打开排行榜界面时读取全球排行，左右切换模式
+pub fn handle_leaderboard_action(
+    mut actions: EventReader<MenuActionEvent>,
+    campaign: Res<Campaign>,
+    mut app_state: ResMut<State<AppState>>,
+    mut leaderboard: ResMut<Leaderboard>,
+) {
+    for MenuActionEvent(action) in actions.iter() {
+        match *action {
+            MenuButtonAction::OpenHighScores => leaderboard.fetch(),
+            MenuButtonAction::CycleHighScores(step) => {
+                let modes = ranked_modes(&campaign);
+                let index = modes
+                    .iter()
+                    .position(|mode| *mode == leaderboard.view)
+                    .unwrap_or(0);
+                leaderboard.view =
+                    modes[(index as i32 + step).rem_euclid(modes.len() as i32) as usize];
+                leaderboard.fetch();
+                let _ = app_state.restart();
+            }
+            _ => {}
+        }
+    }
+}
+
+// 排行榜界面：本地前 10 名和全球排行
+pub fn high_scores_menu(
+    campaign: Res<Campaign>,
+    high_scores: Res<HighScores>,
+    leaderboard: Res<Leaderboard>,
+) -> MenuDescription {
+    let mut local: Vec<String> = high_scores
+        .top(&leaderboard.view.table())
+        .iter()
+        .enumerate()
+        .map(|(i, score)| format!("{}. {}", i + 1, score))
+        .collect();
+    if local.is_empty() {
+        local.push("leaderboard.empty".to_string());
+    }
+    let mut columns = vec![("leaderboard.local".to_string(), local)];
+
+    let global = match &leaderboard.global {
+        GlobalScores::Loaded(entries) if !entries.is_empty() => entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| format!("{}. {} {}", i + 1, entry.player, entry.score))
+            .collect(),
+        GlobalScores::Loaded(_) => vec!["leaderboard.empty".to_string()],
+        GlobalScores::Loading => vec!["leaderboard.loading".to_string()],
+        GlobalScores::Offline => vec!["leaderboard.offline".to_string()],
+        GlobalScores::Disabled => vec!["leaderboard.disabled".to_string()],
+    };
+    for (i, rows) in global.chunks(COLUMN_ROWS).enumerate() {
+        // 只有第一列有标题，其余用空格占位保持对齐
+        let title = if i == 0 { "leaderboard.global" } else { " " };
+        columns.push((title.to_string(), rows.to_vec()));
+    }
+
+    MenuDescription {
+        title: "menu.high_scores.title".to_string(),
+        items: vec![
+            MenuItem::Entry(
+                "leaderboard.mode".to_string(),
+                mode_name(leaderboard.view, &campaign),
+                MenuButtonAction::CycleHighScores(1),
+            ),
+            MenuItem::Columns(columns),
+            MenuItem::Button("menu.back".to_string(), MenuButtonAction::BackToMainMenu),
+        ],
+        back: Some(MenuButtonAction::BackToMainMenu),
+    }
+}
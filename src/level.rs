@@ -0,0 +1,361 @@
+use bevy::{prelude::*, utils::HashSet};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::BoardBorder,
+    campaign::Campaign,
+    hazard::{spawn_hazards, Hazard, HazardSpec},
+    mode::{CurrentRun, GameMode},
+    shrink::MAX_MARGIN,
+    snake::{Direction, Point, SQUARE_SIZE, X, Y},
+    theme::Theme,
+};
+
+// 地图：墙和蛇的起点、初始方向
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct Level {
+    pub walls: Vec<Point>,
+    pub start: Point,
+    pub direction: Direction,
+    // 四周已经变成墙的圈数
+    #[serde(default)]
+    pub margin: i32,
+    // 四周的墙会定时向内收缩
+    #[serde(default)]
+    pub shrinking: bool,
+    // 成对的传送门
+    #[serde(default)]
+    pub portals: Vec<(Point, Point)>,
+    // 会移动的障碍
+    #[serde(default)]
+    pub hazards: Vec<HazardSpec>,
+    // 食物只出现在这些格子里，为空时可以出现在任何空格子
+    #[serde(default)]
+    pub food_zones: Vec<Point>,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self {
+            walls: Vec::new(),
+            start: Point::new(-5, 0),
+            direction: Direction::Right,
+            margin: 0,
+            shrinking: false,
+            portals: Vec::new(),
+            hazards: Vec::new(),
+            food_zones: Vec::new(),
+        }
+    }
+}
+
+// 自定义模式中玩的关卡，由编辑器设置
+#[derive(Resource, Default)]
+pub struct CustomLevel(pub Level);
+
+// 地图上的墙，撞到会死
+#[derive(Component)]
+pub struct Wall;
+
+// 传送门，数字为第几对
+#[derive(Component)]
+pub struct Portal(pub usize);
+
+// 传送门模式中的传送门对数
+const PORTAL_PAIRS: usize = 2;
+
+// 随机地图中墙的段数和每段的长度
+const WALL_SEGMENTS: usize = 8;
+const WALL_LENGTH: std::ops::RangeInclusive<usize> = 2..=5;
+
+impl Level {
+    // 随机生成地图，同一个随机数生成器得到同样的地图
+    pub fn generate(rng: &mut impl Rng) -> Self {
+        let direction = *[
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+            Direction::Left,
+        ]
+        .choose(rng)
+        .unwrap();
+        let mut level = Self {
+            start: Point::new(rng.gen_range(-X + 4..=X - 4), rng.gen_range(-Y + 4..=Y - 4)),
+            direction,
+            ..default()
+        };
+
+        // 起点前后留出空地
+        let mut clear = HashSet::default();
+        let mut ahead = level.start;
+        let mut behind = level.start;
+        for _ in 0..4 {
+            clear.insert(ahead);
+            clear.insert(behind);
+            ahead = ahead.next(&direction);
+            behind = behind.next(&direction.opposite());
+        }
+
+        for _ in 0..WALL_SEGMENTS {
+            let mut point = Point::random(rng);
+            let heading = if rng.gen_bool(0.5) {
+                Direction::Right
+            } else {
+                Direction::Up
+            };
+            for _ in 0..rng.gen_range(WALL_LENGTH) {
+                if point.in_bounds() && !clear.contains(&point) && !level.walls.contains(&point) {
+                    level.walls.push(point);
+                    // 不能把棋盘分成互不连通的区域
+                    if !level.is_connected() {
+                        level.walls.pop();
+                    }
+                }
+                point = point.next(&heading);
+            }
+        }
+        level
+    }
+
+    // 随机放置传送门，离起点的那一行留出空间，墙太多放不下时少放几对
+    pub fn with_random_portals(mut self, rng: &mut impl Rng) -> Self {
+        let mut used = self.snake_body();
+        used.extend(self.walls.iter().copied());
+        let mut free_point = |rng: &mut _| {
+            for _ in 0..1000 {
+                let point = Point::random(rng);
+                let near = used
+                    .iter()
+                    .any(|other| (other.x - point.x).abs() <= 1 && (other.y - point.y).abs() <= 1);
+                if !near && point.y != self.start.y {
+                    used.push(point);
+                    return Some(point);
+                }
+            }
+            None
+        };
+        for _ in 0..PORTAL_PAIRS {
+            if let (Some(a), Some(b)) = (free_point(rng), free_point(rng)) {
+                self.portals.push((a, b));
+            }
+        }
+        self
+    }
+
+    // 按模式生成地图，模式和种子相同时地图相同
+    pub fn for_run(run: &CurrentRun, campaign: &Campaign, custom: &CustomLevel) -> Self {
+        match run.mode {
+            GameMode::Classic | GameMode::TimeAttack(_) | GameMode::Practice => Level::default(),
+            GameMode::Daily => Level::generate(&mut run.level_rng()),
+            GameMode::Shrinking => Level {
+                shrinking: true,
+                ..default()
+            },
+            GameMode::Portals => Level::default().with_random_portals(&mut run.level_rng()),
+            GameMode::Hazards => Level::default().with_random_hazards(&mut run.level_rng()),
+            GameMode::Custom => custom.0.clone(),
+            GameMode::Campaign(index) => campaign
+                .level(index)
+                .map(|level| level.level.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    // 从 point 向 direction 走一格，进入传送门时从配对的传送门出来，方向不变
+    pub fn step(&self, point: &Point, direction: &Direction) -> Point {
+        let next = point.next(direction);
+        self.portal_exit(&next).unwrap_or(next)
+    }
+
+    // 进入传送门后出来的位置
+    pub fn portal_exit(&self, point: &Point) -> Option<Point> {
+        self.portals.iter().find_map(|(a, b)| {
+            if a == point {
+                Some(*b)
+            } else if b == point {
+                Some(*a)
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn is_blocked(&self, point: &Point) -> bool {
+        !self.is_inside(point, self.margin) || self.walls.contains(point)
+    }
+
+    // 下一次收缩时会变成墙的格子，食物不会出现在这里；收缩到头之后不再有
+    pub fn is_doomed(&self, point: &Point) -> bool {
+        self.shrinking && self.margin < MAX_MARGIN && !self.is_inside(point, self.margin + 1)
+    }
+
+    // 去掉四周 margin 圈之后的范围内
+    pub fn is_inside(&self, point: &Point, margin: i32) -> bool {
+        point.x.abs() <= X - margin && point.y.abs() <= Y - margin
+    }
+
+    // 初始的蛇身，从蛇头到蛇尾
+    pub fn snake_body(&self) -> Vec<Point> {
+        let back = self.direction.opposite();
+        let mut points = vec![self.start];
+        for _ in 1..3 {
+            let last = points[points.len() - 1];
+            points.push(last.next(&back));
+        }
+        points
+    }
+
+    // 从起点能走到的格子，可以穿过传送门
+    fn reachable(&self) -> HashSet<Point> {
+        let walls: HashSet<Point> = self.walls.iter().copied().collect();
+        let mut visited = HashSet::default();
+        let mut stack = vec![self.start];
+        while let Some(point) = stack.pop() {
+            if !point.in_bounds() || walls.contains(&point) || !visited.insert(point) {
+                continue;
+            }
+            if let Some(exit) = self.portal_exit(&point) {
+                stack.push(exit);
+            }
+            for direction in [
+                Direction::Up,
+                Direction::Right,
+                Direction::Down,
+                Direction::Left,
+            ] {
+                stack.push(point.next(&direction));
+            }
+        }
+        visited
+    }
+
+    // 所有空格子都能从起点到达
+    fn is_connected(&self) -> bool {
+        let walls: HashSet<Point> = self.walls.iter().copied().collect();
+        let free = ((2 * X + 1) * (2 * Y + 1)) as usize - walls.len();
+        self.reachable().len() == free
+    }
+
+    // 检查关卡能不能玩，错误为字符串表中的 key
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let mut cells = self.snake_body();
+        cells.push(self.start.next(&self.direction));
+        if cells
+            .iter()
+            .any(|point| self.is_blocked(point) || self.portal_exit(point).is_some())
+        {
+            return Err("editor.error.start_blocked");
+        }
+        let reachable = self.reachable();
+        let targets: Vec<Point> = if self.food_zones.is_empty() {
+            let walls: HashSet<Point> = self.walls.iter().copied().collect();
+            (-X..=X)
+                .flat_map(|x| (-Y..=Y).map(move |y| Point::new(x, y)))
+                .filter(|point| !walls.contains(point))
+                .collect()
+        } else {
+            self.food_zones.clone()
+        };
+        if targets.iter().any(|point| !reachable.contains(point)) {
+            return Err("editor.error.unreachable");
+        }
+        Ok(())
+    }
+}
+
+// 按当前模式生成地图，必须在 setup_snake 之前运行
+pub fn setup_level(
+    mut commands: Commands,
+    run: Res<CurrentRun>,
+    custom: Res<CustomLevel>,
+    campaign: Res<Campaign>,
+    mut level: ResMut<Level>,
+    tile_query: Query<Entity, Or<(With<Wall>, With<Portal>, With<Hazard>)>>,
+    theme: Res<Theme>,
+) {
+    *level = Level::for_run(&run, &campaign, &custom);
+
+    for entity in &tile_query {
+        commands.entity(entity).despawn();
+    }
+    spawn_level(&mut commands, &theme, &level);
+    spawn_hazards(&mut commands, &theme, &level);
+}
+
+// 生成墙和传送门，障碍另外生成
+pub fn spawn_level(commands: &mut Commands, theme: &Theme, level: &Level) {
+    for point in &level.walls {
+        spawn_wall(commands, theme, *point);
+    }
+    // 缩圈后外面已经变成墙的格子
+    for x in -X..=X {
+        for y in -Y..=Y {
+            let point = Point::new(x, y);
+            if !level.is_inside(&point, level.margin) {
+                spawn_wall(commands, theme, point);
+            }
+        }
+    }
+    for (i, pair) in level.portals.iter().enumerate() {
+        for point in [pair.0, pair.1] {
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: portal_color(theme, i),
+                        custom_size: Some(Vec2::splat(SQUARE_SIZE * 0.8)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(point.translation())
+                        .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+                    ..default()
+                },
+                Portal(i),
+            ));
+        }
+    }
+}
+
+// 每一对传送门的颜色在主题颜色的基础上改变色相
+pub fn portal_color(theme: &Theme, pair: usize) -> Color {
+    match theme.portal.as_hsla() {
+        Color::Hsla {
+            hue,
+            saturation,
+            lightness,
+            alpha,
+        } => Color::hsla(
+            (hue + pair as f32 * 120.0) % 360.0,
+            saturation,
+            lightness,
+            alpha,
+        ),
+        color => color,
+    }
+}
+
+// 进入编辑器时去掉地图上的墙、传送门和障碍
+pub fn clear_level(
+    mut commands: Commands,
+    tile_query: Query<Entity, Or<(With<Wall>, With<Portal>, With<Hazard>)>>,
+) {
+    for entity in &tile_query {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub fn spawn_wall(commands: &mut Commands, theme: &Theme, point: Point) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: theme.border,
+                custom_size: Some(Vec2::splat(SQUARE_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_translation(point.translation()),
+            ..default()
+        },
+        Wall,
+        BoardBorder,
+    ));
+}
@@ -0,0 +1,32 @@
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+pub mod achievements;
+pub mod board;
+pub mod campaign;
+pub mod clock;
+pub mod common;
+#[cfg(feature = "debug")]
+pub mod debug;
+pub mod editor;
+pub mod effects;
+pub mod game;
+pub mod ghost;
+pub mod hazard;
+pub mod leaderboard;
+pub mod level;
+pub mod locale;
+pub mod menu;
+pub mod mode;
+pub mod replay;
+pub mod rewind;
+pub mod ron_asset;
+pub mod savegame;
+pub mod score;
+pub mod score_server;
+pub mod settings;
+pub mod shrink;
+pub mod snake;
+mod storage;
+pub mod theme;
+pub mod time_attack;
+pub mod verify;
@@ -0,0 +1,172 @@
+use bevy::{asset::LoadState, prelude::*, reflect::TypeUuid, utils::HashMap};
+use serde::Deserialize;
+
+use crate::settings::Settings;
+
+// 内置语言：代码和显示名称，显示名称使用该语言本身
+pub const BUILTIN_LOCALES: [(&str, &str); 2] = [("en", "English"), ("zh-CN", "简体中文")];
+
+// 默认字体没有中文字形，包含 CJK 字符的文字改用这个字体
+const CJK_FONT: &str = "fonts/NotoSansSC-Bold.otf";
+
+// 字符串表，从 assets/locales/*.locale.ron 加载
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "3e1b7d52-9a0c-4f6e-8d2a-5c7b19e4a0f3"]
+pub struct LocaleTable {
+    pub strings: HashMap<String, String>,
+}
+
+// 当前语言的字符串，缺少的条目使用英文
+#[derive(Resource)]
+pub struct Locale {
+    strings: HashMap<String, String>,
+    fallback: HashMap<String, String>,
+}
+
+// 英文字符串表编译进程序，语言文件加载完成之前也能显示
+impl Default for Locale {
+    fn default() -> Self {
+        let table: LocaleTable = ron::from_str(include_str!("../assets/locales/en.locale.ron"))
+            .expect("invalid assets/locales/en.locale.ron");
+        Self {
+            strings: HashMap::default(),
+            fallback: table.strings,
+        }
+    }
+}
+
+impl Locale {
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings
+            .get(key)
+            .or_else(|| self.fallback.get(key))
+            .map_or(key, String::as_str)
+    }
+}
+
+#[derive(Resource)]
+pub struct LocaleHandles(Vec<Option<Handle<LocaleTable>>>);
+
+#[derive(Resource)]
+pub struct UiFonts {
+    pub bold: Handle<Font>,
+    pub mono: Handle<Font>,
+    cjk: Handle<Font>,
+}
+
+impl FromWorld for UiFonts {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        Self {
+            bold: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            mono: asset_server.load("fonts/FiraMono-Medium.ttf"),
+            cjk: asset_server.load(CJK_FONT),
+        }
+    }
+}
+
+impl UiFonts {
+    // 按文字内容选择字体，CJK 字体加载失败时仍使用原字体
+    pub fn pick(
+        &self,
+        text: &str,
+        font: &Handle<Font>,
+        asset_server: &AssetServer,
+    ) -> Handle<Font> {
+        let has_cjk = text.chars().any(|c| c >= '\u{2E80}');
+        if has_cjk && asset_server.get_load_state(&self.cjk) != LoadState::Failed {
+            self.cjk.clone()
+        } else {
+            font.clone()
+        }
+    }
+}
+
+// 需要翻译的文字，记录第一段文字的 key
+#[derive(Component)]
+pub struct Localized(pub String);
+
+// 生成界面文字时翻译并选择字体
+pub struct Translator<'a> {
+    pub locale: &'a Locale,
+    pub fonts: &'a UiFonts,
+    pub asset_server: &'a AssetServer,
+}
+
+impl Translator<'_> {
+    pub fn section(&self, key: &str, style: &TextStyle) -> TextSection {
+        let value = self.locale.get(key).to_string();
+        let font = self.fonts.pick(&value, &style.font, self.asset_server);
+        TextSection::new(
+            value,
+            TextStyle {
+                font,
+                ..style.clone()
+            },
+        )
+    }
+
+    pub fn text(&self, key: &str, style: &TextStyle) -> (TextBundle, Localized) {
+        (
+            TextBundle::from_sections([self.section(key, style)]),
+            Localized(key.to_string()),
+        )
+    }
+}
+
+pub fn setup_locales(mut commands: Commands, asset_server: Res<AssetServer>) {
+    // 英文已经编译进程序
+    let handles = BUILTIN_LOCALES
+        .iter()
+        .map(|(code, _)| {
+            (*code != "en").then(|| asset_server.load(format!("locales/{code}.locale.ron")))
+        })
+        .collect();
+    commands.insert_resource(LocaleHandles(handles));
+}
+
+pub fn select_locale(
+    settings: Res<Settings>,
+    handles: Res<LocaleHandles>,
+    tables: Res<Assets<LocaleTable>>,
+    mut events: EventReader<AssetEvent<LocaleTable>>,
+    mut locale: ResMut<Locale>,
+) {
+    let Some(handle) = handles.0.get(settings.language) else {
+        return;
+    };
+    let reloaded = events.iter().any(|event| match (event, handle) {
+        (AssetEvent::Created { handle: changed }, Some(handle))
+        | (AssetEvent::Modified { handle: changed }, Some(handle)) => changed == handle,
+        _ => false,
+    });
+    if !settings.is_changed() && !reloaded {
+        return;
+    }
+    match handle {
+        Some(handle) => {
+            if let Some(table) = tables.get(handle) {
+                locale.strings = table.strings.clone();
+            }
+        }
+        None => locale.strings.clear(),
+    }
+}
+
+// 切换语言后更新界面上的文字和字体
+pub fn relabel_texts(
+    locale: Res<Locale>,
+    fonts: Res<UiFonts>,
+    asset_server: Res<AssetServer>,
+    mut query: Query<(&mut Text, &Localized)>,
+) {
+    if !locale.is_changed() {
+        return;
+    }
+    for (mut text, localized) in &mut query {
+        let value = locale.get(&localized.0).to_string();
+        let section = &mut text.sections[0];
+        section.style.font = fonts.pick(&value, &fonts.bold, &asset_server);
+        section.value = value;
+    }
+}
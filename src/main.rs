@@ -1,83 +1,36 @@
 use bevy::prelude::*;
-// use bevy_inspector_egui::quick::WorldInspectorPlugin;
-
-use common::*;
-use menu::*;
-use snake::*;
-use score::*;
-
-mod common;
-mod menu;
-mod snake;
-mod score;
 
+#[cfg(feature = "debug")]
+use snake::debug::DebugPlugin;
+use snake::{
+    board::{WINDOW_HEIGHT, WINDOW_WIDTH},
+    game::GamePlugin,
+    verify::verify_command,
+};
 
 fn main() {
-    App::new()
-        .insert_resource(ClearColor(Color::rgb(49.0/255.0, 44.0/255.0, 63.0/255.0)))
-        .insert_resource(Score(0))
-        .insert_resource(Record(0))
-        .add_state(GameState::Quitted)
-        .add_state(AppState::MainMenu)
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            window: WindowDescriptor {
-                title: "snake".to_string(),
-                width: WIDTH + SQUARE_SIZE,
-                height: HEIGHT + SQUARE_SIZE,
-                resizable: false,
-                ..default()
-            },
-            ..default()
-        }))
-        // .add_plugin(WorldInspectorPlugin)
-        .add_startup_system(setup)
-        .add_startup_system(setup_score)
-        .add_startup_system(setup_game_audios)
-        .add_system_set(
-            SystemSet::on_enter(AppState::MainMenu)
-                .with_system(setup_main_menu)
-                .with_system(clear_snake)
-                .with_system(clear_food)
-                .with_system(clear_score)
-                .with_system(setup_snake)
-        )
-        .add_system_set(
-            SystemSet::on_exit(AppState::MainMenu)
-                .with_system(despawn_screen::<OnMainMenuScreen>)
-        )
-        .add_system_set(SystemSet::on_update(AppState::MainMenu).with_system(click_button))
-        .add_system_set(SystemSet::on_update(AppState::InGame).with_system(pause_game))
-
-        // Game Over Menu
-        .add_system_set(SystemSet::on_enter(AppState::GameOver).with_system(setup_game_over_menu))
-        .add_system_set(SystemSet::on_update(AppState::GameOver).with_system(click_button))
-        .add_system_set(
-            SystemSet::on_exit(AppState::GameOver)
-                .with_system(despawn_screen::<OnGameOverMenuScreen>)
-        )
+    // snake verify <replay> 只校验操作记录，不启动游戏
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let [command, path] = &args[..] {
+        if command == "verify" {
+            std::process::exit(verify_command(path));
+        }
+    }
 
-        // Game Playing
-        .add_system_set(
-            SystemSet::on_update(GameState::Playing)
-                .with_system(generate_food)
-                .with_system(move_snake)
-                .with_system(contral_snake)
-                .with_system(update_scoreboard)
-                .with_system(update_recordboard)
-        )
-        // Game Restarted
-        .add_system_set(
-            SystemSet::on_enter(GameState::Restarted)
-                .with_system(clear_snake)
-                .with_system(clear_food)
-                .with_system(clear_score)
-                .with_system(setup_snake)
-        )
-        .add_system_set(SystemSet::on_update(GameState::Restarted).with_system(play_game))
-        
-        .run();
-}
-
-fn setup(mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins.set(WindowPlugin {
+        window: WindowDescriptor {
+            title: "snake".to_string(),
+            width: WINDOW_WIDTH,
+            height: WINDOW_HEIGHT,
+            resizable: false,
+            ..default()
+        },
+        ..default()
+    }))
+    .add_plugin(GamePlugin);
+    // cargo run --features debug
+    #[cfg(feature = "debug")]
+    app.add_plugin(DebugPlugin);
+    app.run();
 }
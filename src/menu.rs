@@ -1,175 +1,354 @@
 use bevy::app::AppExit;
+use bevy::ecs::system::EntityCommands;
 use bevy::prelude::*;
 
 use crate::{
-  common::{AppState, GameState},
+    campaign::Campaign,
+    common::{AppState, PlayState, Transition},
+    editor::EditorAction,
+    locale::{Locale, Translator, UiFonts},
+    mode::{CurrentRun, GameMode},
+    savegame::has_saved_game,
+    score::{RunStats, Score},
+    settings::{Setting, Settings},
+    snake::{DeathCause, FoodKind},
+    theme::{MenuPanel, Theme, ThemedText},
+    time_attack::TIME_ATTACK_LIMITS,
 };
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy)]
 pub enum MenuButtonAction {
-    StartGame,
+    StartGame(GameMode),
+    ContinueGame,
+    ResumeGame,
     RestartGame,
     BackToMainMenu,
+    OpenSettings,
+    OpenAchievements,
+    OpenHighScores,
+    // 排行榜界面切换模式，由 handle_leaderboard_action 处理
+    CycleHighScores(i32),
+    OpenModes,
+    OpenCampaign,
+    OpenEditor,
+    // 试玩结束后回到编辑器
+    BackToEditor,
+    // 编辑器工具栏的按钮，由 handle_editor_action 处理
+    Editor(EditorAction),
+    ChangeSetting(Setting),
+    AdjustSetting(Setting, i32),
     Quit,
 }
 
-#[derive(Component)]
+#[derive(Component, Default)]
 pub struct OnMainMenuScreen;
 
-#[derive(Component)]
+#[derive(Component, Default)]
+pub struct OnSettingsMenuScreen;
+
+#[derive(Component, Default)]
+pub struct OnAchievementsMenuScreen;
+
+#[derive(Component, Default)]
+pub struct OnHighScoresMenuScreen;
+
+#[derive(Component, Default)]
+pub struct OnModeSelectMenuScreen;
+
+#[derive(Component, Default)]
+pub struct OnLevelSelectMenuScreen;
+
+#[derive(Component, Default)]
 pub struct OnGamePausedMenuScreen;
 
-#[derive(Component)]
+#[derive(Component, Default)]
 pub struct OnGameOverMenuScreen;
 
-pub fn setup_main_menu(
-  mut commands: Commands,
-  asset_server: Res<AssetServer>,
-) {
-  commands
-    .spawn((
-        NodeBundle {
-            style: Style {
-                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                justify_content: JustifyContent::Center,
-                flex_direction: FlexDirection::Column,
-                ..default()
-            },
-            ..default()
-        },
-        OnMainMenuScreen,
-    ))
-    .with_children(|parent| {
-      parent
-          .spawn(NodeBundle {
-              style: Style {
-                  flex_direction: FlexDirection::Column,
-                  align_items: AlignItems::Center,
-                  ..default()
-              },
-              background_color: Color::CRIMSON.into(),
-              ..default()
-          })
-          .with_children(|parent| {
-              // 标题
-              parent.spawn(
-                  TextBundle::from_section(
-                      "Snake Main Menu",
-                      TextStyle {
-                          font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                          font_size: 25.0,
-                          color: Color::rgb(0.9, 0.9, 0.9),
-                      },
-                  )
-                  .with_style(Style {
-                      margin: UiRect::all(Val::Px(20.0)),
-                      ..default()
-                  }),
-              );
-
-              // 开始按钮
-              parent
-                  .spawn((
-                      ButtonBundle {
-                          style: Style {
-                              size: Size::new(Val::Px(50.0), Val::Px(30.0)),
-                              margin: UiRect::all(Val::Px(10.0)),
-                              justify_content: JustifyContent::Center,
-                              align_items: AlignItems::Center,
-                              ..default()
-                          },
-                          background_color: Color::rgb(0.15, 0.15, 0.15).into(),
-                          ..default()
-                      },
-                      MenuButtonAction::StartGame,
-                  ))
-                  .with_children(|parent| {
-                      parent.spawn(TextBundle::from_section(
-                          "Start",
-                          TextStyle {
-                              font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                              font_size: 20.0,
-                              color: Color::rgb(0.9, 0.9, 0.9),
-                          },
-                      ));
-                  });
-
-              // 退出按钮
-              parent
-                  .spawn((
-                      ButtonBundle {
-                          style: Style {
-                              size: Size::new(Val::Px(50.0), Val::Px(30.0)),
-                              margin: UiRect::all(Val::Px(10.0)),
-                              justify_content: JustifyContent::Center,
-                              align_items: AlignItems::Center,
-                              ..default()
-                          },
-                          background_color: Color::rgb(0.15, 0.15, 0.15).into(),
-                          ..default()
-                      },
-                      MenuButtonAction::Quit,
-                  ))
-                  .with_children(|parent| {
-                      parent.spawn(TextBundle::from_section(
-                          "Quit",
-                          TextStyle {
-                              font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                              font_size: 20.0,
-                              color: Color::rgb(0.9, 0.9, 0.9),
-                          },
-                      ));
-                  });
-          });
-  });
+#[derive(Component, Default)]
+pub struct OnLevelCompleteMenuScreen;
+
+// 按钮点击、键盘或手柄触发的菜单操作
+pub struct MenuActionEvent(pub MenuButtonAction);
+
+// 菜单根节点，记录按 Escape 时执行的操作
+#[derive(Component)]
+pub struct MenuRoot {
+    back: Option<MenuButtonAction>,
 }
 
-pub fn click_button(
-  mut interaction_query: Query<
-      (&Interaction, &MenuButtonAction),
-      (Changed<Interaction>, With<Button>),
-  >,
-  mut app_state: ResMut<State<AppState>>,
-  mut game_state: ResMut<State<GameState>>,
-  mut exit: EventWriter<AppExit>,
-) {
-  for (interaction, menu_button_action) in &mut interaction_query {
-      match *interaction {
-          Interaction::Clicked => match menu_button_action {
-              MenuButtonAction::StartGame => {
-                  info!("StartGame button clicked");
-                  app_state.set(AppState::InGame).unwrap();
-                  game_state.set(GameState::Playing).unwrap();
-              }
-              MenuButtonAction::RestartGame => {
-                  info!("RestartGame button clicked");
-                  if app_state.current().clone() != AppState::InGame {
-                      app_state.set(AppState::InGame).unwrap();
-                  }
-                  game_state.set(GameState::Restarted).unwrap();
-              }
-              MenuButtonAction::BackToMainMenu => {
-                  info!("BackToMainMenu button clicked");
-                  println!("{:?}", app_state.current());
-                  if app_state.current().clone() != AppState::MainMenu {
-                      app_state.set(AppState::MainMenu).unwrap();
-                  }
-                  if game_state.current().clone() != GameState::Quitted {
-                      game_state.set(GameState::Quitted).unwrap();
-                  }
-              }
-              MenuButtonAction::Quit => {
-                  info!("Quit button clicked");
-                  exit.send_default();
-              }
-          },
-          _ => {}
-      }
-  }
+// 可以用键盘选中的菜单项，数字为顺序
+#[derive(Component)]
+pub struct Focusable(usize);
+
+// 当前选中的菜单项
+#[derive(Resource, Default)]
+pub struct MenuFocus(usize);
+
+// 菜单描述：标题和菜单项，由 spawn_menu 统一生成界面
+// 文字都是字符串表中的 key
+pub struct MenuDescription {
+    pub title: String,
+    pub items: Vec<MenuItem>,
+    // 按 Escape 返回时执行的操作
+    pub back: Option<MenuButtonAction>,
+}
+
+pub enum MenuItem {
+    Button(String, MenuButtonAction),
+    // 带有状态文字的按钮：名称和值，值的显示方式和统计项相同
+    Entry(String, String, MenuButtonAction),
+    // 点击切换的设置项
+    Toggle(Setting),
+    // 用 - + 调节的设置项
+    Slider(Setting),
+    // 只显示的统计项：名称和值，值不在字符串表中时原样显示
+    Stat(String, String),
+    // 并排的几列统计：每列的标题和各行，规则和统计项相同
+    Columns(Vec<(String, Vec<String>)>),
+}
+
+// 设置项当前值的文字
+#[derive(Component)]
+pub struct SettingValueText(Setting);
+
+// 滑动条的填充部分
+#[derive(Component)]
+pub struct SliderFill(Setting);
+
+const BUTTON_WIDTH: f32 = 260.0;
+const BUTTON_HEIGHT: f32 = 30.0;
+const SLIDER_WIDTH: f32 = 60.0;
+
+pub fn main_menu() -> MenuDescription {
+    let mut items = Vec::new();
+    // 有中途退出的一局时可以继续
+    if has_saved_game() {
+        items.push(MenuItem::Button(
+            "menu.continue".to_string(),
+            MenuButtonAction::ContinueGame,
+        ));
+    }
+    items.extend([
+        MenuItem::Button(
+            "menu.start".to_string(),
+            MenuButtonAction::StartGame(GameMode::Classic),
+        ),
+        MenuItem::Button("menu.campaign".to_string(), MenuButtonAction::OpenCampaign),
+        MenuItem::Button("menu.modes".to_string(), MenuButtonAction::OpenModes),
+        MenuItem::Button("menu.editor".to_string(), MenuButtonAction::OpenEditor),
+        MenuItem::Button("menu.settings".to_string(), MenuButtonAction::OpenSettings),
+        MenuItem::Button(
+            "menu.achievements".to_string(),
+            MenuButtonAction::OpenAchievements,
+        ),
+        MenuItem::Button(
+            "menu.high_scores".to_string(),
+            MenuButtonAction::OpenHighScores,
+        ),
+        MenuItem::Button("menu.quit".to_string(), MenuButtonAction::Quit),
+    ]);
+    MenuDescription {
+        title: "menu.main.title".to_string(),
+        items,
+        back: None,
+    }
+}
+
+// 其他游戏模式
+pub fn mode_menu() -> MenuDescription {
+    let mut items = vec![MenuItem::Button(
+        "menu.daily".to_string(),
+        MenuButtonAction::StartGame(GameMode::Daily),
+    )];
+    for seconds in TIME_ATTACK_LIMITS {
+        items.push(MenuItem::Button(
+            format!("menu.time_attack_{seconds}"),
+            MenuButtonAction::StartGame(GameMode::TimeAttack(seconds)),
+        ));
+    }
+    items.push(MenuItem::Button(
+        "menu.shrinking".to_string(),
+        MenuButtonAction::StartGame(GameMode::Shrinking),
+    ));
+    items.push(MenuItem::Button(
+        "menu.portals".to_string(),
+        MenuButtonAction::StartGame(GameMode::Portals),
+    ));
+    items.push(MenuItem::Button(
+        "menu.hazards".to_string(),
+        MenuButtonAction::StartGame(GameMode::Hazards),
+    ));
+    items.push(MenuItem::Button(
+        "menu.practice".to_string(),
+        MenuButtonAction::StartGame(GameMode::Practice),
+    ));
+    items.push(MenuItem::Button(
+        "menu.back".to_string(),
+        MenuButtonAction::BackToMainMenu,
+    ));
+    MenuDescription {
+        title: "menu.modes.title".to_string(),
+        items,
+        back: Some(MenuButtonAction::BackToMainMenu),
+    }
+}
+
+pub fn settings_menu() -> MenuDescription {
+    let mut items: Vec<MenuItem> = Setting::ALL
+        .into_iter()
+        .map(|setting| {
+            if setting.is_slider() {
+                MenuItem::Slider(setting)
+            } else {
+                MenuItem::Toggle(setting)
+            }
+        })
+        .collect();
+    items.push(MenuItem::Button(
+        "menu.back".to_string(),
+        MenuButtonAction::BackToMainMenu,
+    ));
+    MenuDescription {
+        title: "menu.settings.title".to_string(),
+        items,
+        back: Some(MenuButtonAction::BackToMainMenu),
+    }
+}
+
+pub fn pause_menu(run: Res<CurrentRun>) -> MenuDescription {
+    let mut items = vec![
+        MenuItem::Button("menu.resume".to_string(), MenuButtonAction::ResumeGame),
+        MenuItem::Button("menu.restart".to_string(), MenuButtonAction::RestartGame),
+    ];
+    if run.mode == GameMode::Custom {
+        items.push(MenuItem::Button(
+            "menu.back_to_editor".to_string(),
+            MenuButtonAction::BackToEditor,
+        ));
+    }
+    items.push(MenuItem::Button(
+        "menu.main_menu".to_string(),
+        MenuButtonAction::BackToMainMenu,
+    ));
+    MenuDescription {
+        title: "menu.paused.title".to_string(),
+        items,
+        back: Some(MenuButtonAction::ResumeGame),
+    }
+}
+
+pub fn game_over_menu(
+    score: Res<Score>,
+    stats: Res<RunStats>,
+    run: Res<CurrentRun>,
+    campaign: Res<Campaign>,
+) -> MenuDescription {
+    let stat = |label: &str, value: String| MenuItem::Stat(label.to_string(), value);
+    let seconds = stats.time as u32;
+    let mut items = vec![stat("summary.mode", run.mode.label().to_string())];
+    if let Some(date) = &run.date {
+        items.push(stat("summary.date", date.clone()));
+    }
+    if let GameMode::TimeAttack(seconds) = run.mode {
+        items.push(stat("summary.time_limit", format!("{seconds}s")));
+    }
+    if let Some(level) = match run.mode {
+        GameMode::Campaign(index) => campaign.level(index),
+        _ => None,
+    } {
+        items.push(stat("summary.level", level.name()));
+    }
+    items.extend([
+        stat("summary.score", score.0.to_string()),
+        stat("summary.length", stats.length.to_string()),
+        stat(
+            "summary.time",
+            format!("{}:{:02}", seconds / 60, seconds % 60),
+        ),
+    ]);
+    for kind in FoodKind::ALL {
+        let eaten = stats.food_eaten.get(&kind).copied().unwrap_or(0);
+        // 没吃到的特殊食物不显示
+        if eaten > 0 || kind == FoodKind::Normal {
+            items.push(stat(kind.label(), eaten.to_string()));
+        }
+    }
+    if let Some(death) = stats.death {
+        items.push(match death.cause {
+            DeathCause::Wall => stat("summary.hit_wall", death.point.to_string()),
+            DeathCause::SelfHit => stat("summary.hit_self", death.point.to_string()),
+            DeathCause::TimeUp => stat("summary.time_up", String::new()),
+            DeathCause::Hazard => stat("summary.hit_hazard", death.point.to_string()),
+        });
+    }
+    items.push(match stats.rank {
+        Some(rank) => stat("summary.rank", format!("#{rank}")),
+        None if !run.counted => stat("summary.rank", "summary.practice".to_string()),
+        None => stat("summary.rank", "summary.unranked".to_string()),
+    });
+    if stats.new_record {
+        items.push(stat("summary.new_record", String::new()));
+    }
+    items.push(MenuItem::Button(
+        "menu.main_menu".to_string(),
+        MenuButtonAction::BackToMainMenu,
+    ));
+    items.push(MenuItem::Button(
+        "menu.restart".to_string(),
+        MenuButtonAction::RestartGame,
+    ));
+    if run.mode == GameMode::Custom {
+        items.push(MenuItem::Button(
+            "menu.back_to_editor".to_string(),
+            MenuButtonAction::BackToEditor,
+        ));
+    }
+    if let GameMode::Campaign(_) = run.mode {
+        items.push(MenuItem::Button(
+            "menu.level_select".to_string(),
+            MenuButtonAction::OpenCampaign,
+        ));
+    }
+    MenuDescription {
+        title: "menu.game_over.title".to_string(),
+        items,
+        back: Some(MenuButtonAction::BackToMainMenu),
+    }
 }
 
+// 根据菜单描述生成界面，M 是退出时 despawn_screen 使用的标记
+pub fn spawn_menu<M: Component + Default>(
+    In(description): In<MenuDescription>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    theme: Res<Theme>,
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+    fonts: Res<UiFonts>,
+    mut focus: ResMut<MenuFocus>,
+) {
+    focus.0 = 0;
+    let translator = Translator {
+        locale: &locale,
+        fonts: &fonts,
+        asset_server: &asset_server,
+    };
+    let title_style = TextStyle {
+        font: fonts.bold.clone(),
+        font_size: 25.0,
+        color: theme.title_text,
+    };
+    let text_style = TextStyle {
+        font: fonts.bold.clone(),
+        font_size: 20.0,
+        color: theme.button_text,
+    };
+    let stat_style = TextStyle {
+        font_size: 18.0,
+        color: theme.title_text,
+        ..text_style.clone()
+    };
 
-pub fn setup_game_over_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands
         .spawn((
             NodeBundle {
@@ -181,94 +360,513 @@ pub fn setup_game_over_menu(mut commands: Commands, asset_server: Res<AssetServe
                 },
                 ..default()
             },
-            OnGameOverMenuScreen,
+            M::default(),
+            MenuRoot {
+                back: description.back,
+            },
         ))
         .with_children(|parent| {
             parent
-                .spawn(NodeBundle {
-                    style: Style {
-                        flex_direction: FlexDirection::Column,
-                        align_items: AlignItems::Center,
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            padding: UiRect::all(Val::Px(10.0)),
+                            ..default()
+                        },
+                        background_color: theme.menu_background.into(),
                         ..default()
                     },
-                    background_color: Color::CRIMSON.into(),
-                    ..default()
-                })
+                    MenuPanel,
+                ))
                 .with_children(|parent| {
                     // 标题
-                    parent.spawn(
-                        TextBundle::from_section(
-                            "Game Over",
-                            TextStyle {
-                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                font_size: 25.0,
-                                color: Color::rgb(0.9, 0.9, 0.9),
-                            },
-                        )
-                        .with_style(Style {
+                    let (title, localized) = translator.text(&description.title, &title_style);
+                    parent.spawn((
+                        title.with_style(Style {
                             margin: UiRect::all(Val::Px(20.0)),
                             ..default()
                         }),
-                    );
-
-                    // 返回主菜单按钮
-                    parent
-                        .spawn((
-                            ButtonBundle {
-                                style: Style {
-                                    size: Size::new(Val::Px(90.0), Val::Px(30.0)),
-                                    margin: UiRect::all(Val::Px(10.0)),
-                                    justify_content: JustifyContent::Center,
-                                    align_items: AlignItems::Center,
-                                    ..default()
-                                },
-                                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
-                                ..default()
-                            },
-                            MenuButtonAction::BackToMainMenu,
-                        ))
-                        .with_children(|parent| {
-                            parent.spawn(TextBundle::from_section(
-                                "Main Menu",
-                                TextStyle {
-                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                    font_size: 20.0,
-                                    color: Color::rgb(0.9, 0.9, 0.9),
-                                },
-                            ));
-                        });
-
-                    // 重新开始按钮
-                    parent
-                        .spawn((
-                            ButtonBundle {
-                                style: Style {
-                                    size: Size::new(Val::Px(90.0), Val::Px(30.0)),
-                                    margin: UiRect::all(Val::Px(10.0)),
-                                    justify_content: JustifyContent::Center,
-                                    align_items: AlignItems::Center,
-                                    ..default()
-                                },
-                                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
-                                ..default()
-                            },
-                            MenuButtonAction::RestartGame,
-                        ))
-                        .with_children(|parent| {
-                            parent.spawn(TextBundle::from_section(
-                                "Restart",
-                                TextStyle {
-                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                    font_size: 20.0,
-                                    color: Color::rgb(0.9, 0.9, 0.9),
-                                },
-                            ));
-                        });
+                        localized,
+                        ThemedText::Title,
+                    ));
+
+                    // 统计项不能选中，按可选中的菜单项单独编号
+                    let mut orders = 0..;
+                    for item in description.items {
+                        match item {
+                            MenuItem::Button(label, action) => {
+                                spawn_button(parent, &theme, BUTTON_WIDTH, action)
+                                    .insert(Focusable(orders.next().unwrap()))
+                                    .with_children(|parent| {
+                                        parent.spawn((
+                                            translator.text(&label, &text_style),
+                                            ThemedText::Button,
+                                        ));
+                                    });
+                            }
+                            MenuItem::Entry(label, value, action) => {
+                                let mut label = translator.section(&label, &text_style);
+                                label.value.push_str(": ");
+                                spawn_button(parent, &theme, BUTTON_WIDTH, action)
+                                    .insert(Focusable(orders.next().unwrap()))
+                                    .with_children(|parent| {
+                                        parent.spawn((
+                                            TextBundle::from_sections([
+                                                label,
+                                                translator.section(&value, &text_style),
+                                            ]),
+                                            ThemedText::Button,
+                                        ));
+                                    });
+                            }
+                            MenuItem::Toggle(setting) => {
+                                let action = MenuButtonAction::ChangeSetting(setting);
+                                spawn_button(parent, &theme, BUTTON_WIDTH, action)
+                                    .insert(Focusable(orders.next().unwrap()))
+                                    .with_children(|parent| {
+                                        parent.spawn((
+                                            TextBundle::from_sections(setting_sections(
+                                                &translator,
+                                                &settings,
+                                                setting,
+                                                &text_style,
+                                            )),
+                                            SettingValueText(setting),
+                                            ThemedText::Button,
+                                        ));
+                                    });
+                            }
+                            MenuItem::Slider(setting) => {
+                                spawn_slider(
+                                    parent,
+                                    &theme,
+                                    &settings,
+                                    &translator,
+                                    &text_style,
+                                    setting,
+                                )
+                                .insert(Focusable(orders.next().unwrap()));
+                            }
+                            MenuItem::Stat(label, value) => {
+                                let mut label = translator.section(&label, &stat_style);
+                                if !value.is_empty() {
+                                    label.value.push_str(": ");
+                                }
+                                parent.spawn((
+                                    TextBundle::from_sections([
+                                        label,
+                                        translator.section(&value, &stat_style),
+                                    ])
+                                    .with_style(Style {
+                                        margin: UiRect::all(Val::Px(3.0)),
+                                        ..default()
+                                    }),
+                                    ThemedText::Title,
+                                ));
+                            }
+                            MenuItem::Columns(columns) => {
+                                spawn_columns(parent, &translator, &stat_style, columns);
+                            }
+                        }
+                    }
                 });
         });
 }
 
+fn spawn_columns(
+    parent: &mut ChildBuilder,
+    translator: &Translator,
+    style: &TextStyle,
+    columns: Vec<(String, Vec<String>)>,
+) {
+    let row_style = TextStyle {
+        font_size: 16.0,
+        ..style.clone()
+    };
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                align_items: AlignItems::FlexStart,
+                margin: UiRect::all(Val::Px(5.0)),
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            for (title, rows) in columns {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            margin: UiRect::horizontal(Val::Px(10.0)),
+                            ..default()
+                        },
+                        background_color: Color::NONE.into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn((translator.text(&title, style), ThemedText::Title));
+                        for row in rows {
+                            parent.spawn((translator.text(&row, &row_style), ThemedText::Title));
+                        }
+                    });
+            }
+        });
+}
+
+// 设置项的文字：名称和当前值
+fn setting_sections(
+    translator: &Translator,
+    settings: &Settings,
+    setting: Setting,
+    style: &TextStyle,
+) -> [TextSection; 2] {
+    let mut label = translator.section(setting.label(), style);
+    label.value.push_str(": ");
+    [
+        label,
+        translator.section(&settings.value_text(setting), style),
+    ]
+}
+
+pub fn spawn_button<'w, 's, 'a>(
+    parent: &'a mut ChildBuilder<'w, 's, '_>,
+    theme: &Theme,
+    width: f32,
+    action: MenuButtonAction,
+) -> EntityCommands<'w, 's, 'a> {
+    parent.spawn((
+        ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(width), Val::Px(BUTTON_HEIGHT)),
+                margin: UiRect::all(Val::Px(10.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: theme.button.into(),
+            ..default()
+        },
+        action,
+    ))
+}
+
+// 标签  -  [■■■□□]  +
+// 选中整行时可以用左右方向键调节
+fn spawn_slider<'w, 's, 'a>(
+    parent: &'a mut ChildBuilder<'w, 's, '_>,
+    theme: &Theme,
+    settings: &Settings,
+    translator: &Translator,
+    text_style: &TextStyle,
+    setting: Setting,
+) -> EntityCommands<'w, 's, 'a> {
+    let step_button = |parent: &mut ChildBuilder, label: &str, step: i32| {
+        let action = MenuButtonAction::AdjustSetting(setting, step);
+        spawn_button(parent, theme, BUTTON_HEIGHT, action).with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(label, text_style.clone()),
+                ThemedText::Button,
+            ));
+        });
+    };
 
+    let mut row = parent.spawn((
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(BUTTON_WIDTH), Val::Px(BUTTON_HEIGHT)),
+                margin: UiRect::all(Val::Px(10.0)),
+                padding: UiRect::horizontal(Val::Px(10.0)),
+                justify_content: JustifyContent::SpaceBetween,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        },
+        MenuButtonAction::ChangeSetting(setting),
+    ));
+    row.with_children(|parent| {
+        parent.spawn((
+            translator.text(setting.label(), text_style),
+            ThemedText::Button,
+        ));
+        step_button(parent, "-", -1);
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Px(SLIDER_WIDTH), Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: theme.button.into(),
+                ..default()
+            })
+            .with_children(|parent| {
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            size: Size::new(
+                                Val::Percent(settings.fraction(setting) * 100.0),
+                                Val::Percent(100.0),
+                            ),
+                            ..default()
+                        },
+                        background_color: theme.button_text.into(),
+                        ..default()
+                    },
+                    SliderFill(setting),
+                ));
+            });
+        step_button(parent, "+", 1);
+    });
+    row
+}
+
+pub fn click_button(
+    interaction_query: Query<
+        (&Interaction, &MenuButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut actions: EventWriter<MenuActionEvent>,
+) {
+    for (interaction, menu_button_action) in &interaction_query {
+        if *interaction == Interaction::Clicked {
+            actions.send(MenuActionEvent(*menu_button_action));
+        }
+    }
+}
+
+// 键盘：上下/Tab 选择，回车/空格确认，左右调节，Escape 返回
+// 手柄：十字键选择和调节，A 确认，B 返回
+pub fn navigate_menu(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    root_query: Query<&MenuRoot>,
+    focusable_query: Query<(&Focusable, &MenuButtonAction)>,
+    hover_query: Query<(&Focusable, &Interaction), Changed<Interaction>>,
+    mut focus: ResMut<MenuFocus>,
+    mut actions: EventWriter<MenuActionEvent>,
+) {
+    let Ok(root) = root_query.get_single() else {
+        return;
+    };
+    let gamepad_pressed = |button_type| {
+        gamepads
+            .iter()
+            .any(|gamepad| gamepad_input.just_pressed(GamepadButton::new(gamepad, button_type)))
+    };
+    let shift = keyboard_input.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+    let tab = keyboard_input.just_pressed(KeyCode::Tab);
+    let up = keyboard_input.just_pressed(KeyCode::Up)
+        || (tab && shift)
+        || gamepad_pressed(GamepadButtonType::DPadUp);
+    let down = keyboard_input.just_pressed(KeyCode::Down)
+        || (tab && !shift)
+        || gamepad_pressed(GamepadButtonType::DPadDown);
+    let left =
+        keyboard_input.just_pressed(KeyCode::Left) || gamepad_pressed(GamepadButtonType::DPadLeft);
+    let right = keyboard_input.just_pressed(KeyCode::Right)
+        || gamepad_pressed(GamepadButtonType::DPadRight);
+    let confirm = keyboard_input.any_just_pressed([KeyCode::Return, KeyCode::Space])
+        || gamepad_pressed(GamepadButtonType::South);
+    let back =
+        keyboard_input.just_pressed(KeyCode::Escape) || gamepad_pressed(GamepadButtonType::East);
+
+    // 鼠标移到的菜单项同时成为选中项
+    for (focusable, interaction) in &hover_query {
+        if *interaction == Interaction::Hovered {
+            focus.0 = focusable.0;
+        }
+    }
+
+    let count = focusable_query.iter().count();
+    if count == 0 {
+        return;
+    }
+    if up {
+        focus.0 = (focus.0 + count - 1) % count;
+    }
+    if down {
+        focus.0 = (focus.0 + 1) % count;
+    }
+
+    let focused = focusable_query
+        .iter()
+        .find(|(focusable, _)| focusable.0 == focus.0)
+        .map(|(_, action)| *action);
+    if let Some(action) = focused {
+        if confirm {
+            actions.send(MenuActionEvent(action));
+        }
+        match action {
+            MenuButtonAction::ChangeSetting(setting) => {
+                if left {
+                    actions.send(MenuActionEvent(MenuButtonAction::AdjustSetting(
+                        setting, -1,
+                    )));
+                }
+                if right {
+                    actions.send(MenuActionEvent(MenuButtonAction::AdjustSetting(setting, 1)));
+                }
+            }
+            MenuButtonAction::CycleHighScores(_) => {
+                if left {
+                    actions.send(MenuActionEvent(MenuButtonAction::CycleHighScores(-1)));
+                }
+                if right {
+                    actions.send(MenuActionEvent(MenuButtonAction::CycleHighScores(1)));
+                }
+            }
+            _ => {}
+        }
+    }
+    if back {
+        if let Some(action) = root.back {
+            actions.send(MenuActionEvent(action));
+        }
+    }
+}
+
+pub fn handle_menu_action(
+    mut actions: EventReader<MenuActionEvent>,
+    mut app_state: ResMut<State<AppState>>,
+    mut settings: ResMut<Settings>,
+    mut run: ResMut<CurrentRun>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for MenuActionEvent(action) in actions.iter() {
+        match action {
+            MenuButtonAction::StartGame(mode) => {
+                info!("StartGame button clicked");
+                // 按选择的模式重新生成地图和蛇
+                if app_state.transition(AppState::InGame(PlayState::Starting)) {
+                    run.mode = *mode;
+                }
+            }
+            MenuButtonAction::ContinueGame => {
+                info!("ContinueGame button clicked");
+                app_state.transition(AppState::InGame(PlayState::Resuming));
+            }
+            MenuButtonAction::ResumeGame => {
+                info!("ResumeGame button clicked");
+                app_state.transition(AppState::InGame(PlayState::Running));
+            }
+            MenuButtonAction::RestartGame => {
+                info!("RestartGame button clicked");
+                app_state.transition(AppState::InGame(PlayState::Starting));
+            }
+            MenuButtonAction::BackToMainMenu => {
+                info!("BackToMainMenu button clicked");
+                app_state.transition(AppState::MainMenu);
+            }
+            MenuButtonAction::OpenSettings => {
+                info!("OpenSettings button clicked");
+                app_state.transition(AppState::Settings);
+            }
+            MenuButtonAction::OpenModes => {
+                info!("OpenModes button clicked");
+                app_state.transition(AppState::ModeSelect);
+            }
+            MenuButtonAction::OpenCampaign => {
+                info!("OpenCampaign button clicked");
+                app_state.transition(AppState::LevelSelect);
+            }
+            MenuButtonAction::OpenEditor => {
+                info!("OpenEditor button clicked");
+                app_state.transition(AppState::Editor);
+            }
+            MenuButtonAction::BackToEditor => {
+                info!("BackToEditor button clicked");
+                app_state.transition(AppState::Editor);
+            }
+            // 编辑器自己处理
+            MenuButtonAction::Editor(_) => {}
+            MenuButtonAction::OpenAchievements => {
+                info!("OpenAchievements button clicked");
+                app_state.transition(AppState::Achievements);
+            }
+            MenuButtonAction::OpenHighScores => {
+                info!("OpenHighScores button clicked");
+                app_state.transition(AppState::HighScores);
+            }
+            // 排行榜界面自己处理
+            MenuButtonAction::CycleHighScores(_) => {}
+            MenuButtonAction::ChangeSetting(setting) => {
+                info!("ChangeSetting button clicked");
+                settings.change(*setting);
+            }
+            MenuButtonAction::AdjustSetting(setting, step) => {
+                info!("AdjustSetting button clicked");
+                settings.adjust(*setting, *step);
+            }
+            MenuButtonAction::Quit => {
+                info!("Quit button clicked");
+                exit.send_default();
+            }
+        }
+    }
+}
+
+// 鼠标悬停、按下和键盘选中时按钮变色
+pub fn button_colors(
+    theme: Res<Theme>,
+    focus: Res<MenuFocus>,
+    mut query: Query<
+        (
+            Option<&Interaction>,
+            Option<&Focusable>,
+            &mut BackgroundColor,
+        ),
+        Or<(With<Button>, With<Focusable>)>,
+    >,
+) {
+    for (interaction, focusable, mut background) in &mut query {
+        let focused = focusable.is_some_and(|focusable| focusable.0 == focus.0);
+        let color = match interaction {
+            Some(Interaction::Clicked) => theme.button_pressed,
+            Some(Interaction::Hovered) => theme.button_hovered,
+            _ if focused => theme.button_hovered,
+            Some(Interaction::None) => theme.button,
+            None => Color::NONE,
+        };
+        if background.0 != color {
+            *background = color.into();
+        }
+    }
+}
+
+// 设置或语言改变后更新菜单上显示的值
+pub fn update_menu_widgets(
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+    fonts: Res<UiFonts>,
+    asset_server: Res<AssetServer>,
+    mut text_query: Query<(&mut Text, &SettingValueText)>,
+    mut fill_query: Query<(&mut Style, &SliderFill)>,
+) {
+    if !settings.is_changed() && !locale.is_changed() {
+        return;
+    }
+    let translator = Translator {
+        locale: &locale,
+        fonts: &fonts,
+        asset_server: &asset_server,
+    };
+    for (mut text, value_text) in &mut text_query {
+        let style = TextStyle {
+            font: fonts.bold.clone(),
+            ..text.sections[0].style.clone()
+        };
+        text.sections = setting_sections(&translator, &settings, value_text.0, &style).into();
+    }
+    for (mut style, fill) in &mut fill_query {
+        style.size.width = Val::Percent(settings.fraction(fill.0) * 100.0);
+    }
+}
 
 pub fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
     for entity in &to_despawn {
@@ -276,7 +874,6 @@ pub fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut comm
     }
 }
 
-
-pub fn play_game(mut game_state: ResMut<State<GameState>>) {
-    game_state.set(GameState::Playing).unwrap();
-}
\ No newline at end of file
+pub fn play_game(mut app_state: ResMut<State<AppState>>) {
+    app_state.transition(AppState::InGame(PlayState::Running));
+}
@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{snake::Invincible, storage};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameMode {
+    #[default]
+    Classic,
+    // 每日挑战：地图、起点和食物由当天日期决定
+    Daily,
+    // 限时模式，参数为限定的秒数
+    TimeAttack(u32),
+    // 四周的墙定时向内收缩，得分为存活秒数加食物分数
+    Shrinking,
+    // 随机放置成对的传送门
+    Portals,
+    // 随机放置会移动的障碍
+    Hazards,
+    // 试玩编辑器中的关卡，不计入排行榜
+    Custom,
+    // 闯关模式，参数为第几关
+    Campaign(usize),
+    // 练习模式：可以倒退时间，不计入排行榜
+    Practice,
+}
+
+impl GameMode {
+    // 字符串表中的 key
+    pub fn label(&self) -> &'static str {
+        match *self {
+            GameMode::Classic => "mode.classic",
+            GameMode::Daily => "mode.daily",
+            GameMode::TimeAttack(_) => "mode.time_attack",
+            GameMode::Shrinking => "mode.shrinking",
+            GameMode::Portals => "mode.portals",
+            GameMode::Hazards => "mode.hazards",
+            GameMode::Custom => "mode.custom",
+            GameMode::Campaign(_) => "mode.campaign",
+            GameMode::Practice => "mode.practice",
+        }
+    }
+
+    // 种子固定的模式，每次玩到的地图和食物相同
+    pub fn is_seeded(&self) -> bool {
+        matches!(self, GameMode::Daily | GameMode::Campaign(_))
+    }
+
+    // 排行榜名称，不同模式分开记录
+    pub fn table(&self) -> String {
+        match *self {
+            GameMode::Classic => "classic".to_string(),
+            GameMode::Daily => "daily".to_string(),
+            GameMode::TimeAttack(seconds) => format!("time_attack_{seconds}"),
+            GameMode::Shrinking => "shrinking".to_string(),
+            GameMode::Portals => "portals".to_string(),
+            GameMode::Hazards => "hazards".to_string(),
+            GameMode::Custom => "custom".to_string(),
+            GameMode::Campaign(index) => format!("campaign_{index}"),
+            GameMode::Practice => "practice".to_string(),
+        }
+    }
+}
+
+// 每日挑战的全球排行榜每天一个，例如 daily_2023-02-01
+pub fn daily_table(date: &str) -> String {
+    format!("daily_{date}")
+}
+
+// 当前一局的模式和随机种子，相同的种子生成相同的地图和食物
+#[derive(Resource, Default, Clone, Serialize, Deserialize)]
+pub struct CurrentRun {
+    pub mode: GameMode,
+    pub seed: u64,
+    // 每日挑战的日期
+    pub date: Option<String>,
+    // 是否计入排行榜，每日挑战只有当天第一次计入
+    pub counted: bool,
+    food_count: u64,
+}
+
+impl CurrentRun {
+    // 从头开始的一局，用于重新模拟操作记录
+    pub fn new(mode: GameMode, seed: u64) -> Self {
+        Self {
+            mode,
+            seed,
+            ..default()
+        }
+    }
+
+    // 关卡使用最后一个随机数流，食物从 0 开始依次使用
+    pub fn level_rng(&self) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        rng.set_stream(u64::MAX);
+        rng
+    }
+
+    // 每个食物使用独立的随机数流，不受蛇的走法影响
+    pub fn food_rng(&mut self) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        rng.set_stream(self.food_count);
+        self.food_count += 1;
+        rng
+    }
+}
+
+// 每日挑战第一次的成绩，按日期保存
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct DailyResults(BTreeMap<String, u32>);
+
+const DAILY_RESULTS_FILE: &str = "daily.ron";
+
+impl DailyResults {
+    pub fn record(&mut self, date: &str, score: u32) {
+        self.0.insert(date.to_string(), score);
+        storage::save(DAILY_RESULTS_FILE, self);
+    }
+}
+
+pub fn setup_daily_results(mut commands: Commands) {
+    commands.insert_resource(storage::load::<DailyResults>(DAILY_RESULTS_FILE));
+}
+
+// 调试控制台指定的种子，只用于下一局，不计入排行榜
+#[derive(Resource, Default)]
+pub struct NextSeed(pub Option<u64>);
+
+// 开始新的一局，重新开始时模式不变
+pub fn start_run(
+    mut run: ResMut<CurrentRun>,
+    mut daily: ResMut<DailyResults>,
+    mut next_seed: ResMut<NextSeed>,
+    invincible: Res<Invincible>,
+) {
+    let mode = run.mode;
+    *run = match mode {
+        GameMode::Daily => {
+            let date = today();
+            // 开局就记下当天的挑战，中途退出也不能重来
+            let counted = !daily.0.contains_key(&date);
+            if counted {
+                daily.record(&date, 0);
+            }
+            CurrentRun {
+                mode,
+                seed: seed_from_str(&date),
+                date: Some(date),
+                counted,
+                ..default()
+            }
+        }
+        // 闯关的每一关食物出现的顺序固定
+        GameMode::Campaign(_) => CurrentRun {
+            mode,
+            seed: seed_from_str(&mode.table()),
+            counted: true,
+            ..default()
+        },
+        _ => CurrentRun {
+            mode,
+            seed: random(),
+            counted: !matches!(mode, GameMode::Custom | GameMode::Practice),
+            ..default()
+        },
+    };
+    if let Some(seed) = next_seed.0.take() {
+        run.seed = seed;
+        run.counted = false;
+    }
+    if invincible.0 {
+        run.counted = false;
+    }
+}
+
+// FNV-1a，不同平台和版本结果一致
+pub fn seed_from_str(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// 当前的 UTC 日期，例如 2023-02-01
+pub fn today() -> String {
+    // 公历日期换算，参考 http://howardhinnant.github.io/date_algorithms.html
+    let days = (unix_seconds() / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn unix_seconds() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn unix_seconds() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
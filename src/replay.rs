@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    mode::{daily_table, CurrentRun, GameMode},
+    score::Score,
+    snake::{Direction, FoodEatenEvent, SnakeMovedEvent},
+    storage,
+};
+
+const REPLAYS_FILE: &str = "replays.ron";
+
+// 一局的操作记录，种子固定的模式可以据此重现整局
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub mode: GameMode,
+    pub seed: u64,
+    // 每日挑战的日期，种子由日期决定
+    #[serde(default)]
+    pub date: Option<String>,
+    pub score: u32,
+    // 每一步移动的方向
+    pub steps: Vec<Direction>,
+    // 吃到食物的是第几步
+    pub eaten: Vec<usize>,
+}
+
+impl Replay {
+    // 全球排行榜名称，每日挑战按日期分开
+    pub fn table(&self) -> String {
+        match (self.mode, &self.date) {
+            (GameMode::Daily, Some(date)) => daily_table(date),
+            (mode, _) => mode.table(),
+        }
+    }
+
+    // 分数高的更好，分数相同时步数少的更好
+    fn is_better_than(&self, other: &Replay) -> bool {
+        (self.score, std::cmp::Reverse(self.steps.len()))
+            > (other.score, std::cmp::Reverse(other.steps.len()))
+    }
+}
+
+// 种子固定的模式中每个种子的最好记录，保存在数据目录
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct Replays(BTreeMap<String, Replay>);
+
+impl Replays {
+    pub fn best(&self, run: &CurrentRun) -> Option<&Replay> {
+        self.0.get(&replay_key(run)?)
+    }
+}
+
+fn replay_key(run: &CurrentRun) -> Option<String> {
+    run.mode
+        .is_seeded()
+        .then(|| format!("{}_{:016x}", run.mode.table(), run.seed))
+}
+
+// 本局的操作记录，继续保存的一局时记录不完整，不再保存
+#[derive(Resource, Default)]
+pub struct RunRecorder {
+    steps: Vec<Direction>,
+    eaten: Vec<usize>,
+    complete: bool,
+}
+
+impl RunRecorder {
+    // 从头记录的一局才能重现
+    pub fn replay(&self, run: &CurrentRun, score: u32) -> Option<Replay> {
+        self.complete.then(|| Replay {
+            mode: run.mode,
+            seed: run.seed,
+            date: run.date.clone(),
+            score,
+            steps: self.steps.clone(),
+            eaten: self.eaten.clone(),
+        })
+    }
+}
+
+pub fn setup_replays(mut commands: Commands) {
+    commands.insert_resource(storage::load::<Replays>(REPLAYS_FILE));
+}
+
+pub fn reset_recorder(mut recorder: ResMut<RunRecorder>) {
+    *recorder = RunRecorder {
+        complete: true,
+        ..default()
+    };
+}
+
+pub fn discard_recorder(mut recorder: ResMut<RunRecorder>) {
+    *recorder = RunRecorder::default();
+}
+
+pub fn record_steps(
+    mut moved_events: EventReader<SnakeMovedEvent>,
+    mut food_events: EventReader<FoodEatenEvent>,
+    mut recorder: ResMut<RunRecorder>,
+) {
+    for event in moved_events.iter() {
+        recorder.steps.push(event.direction);
+    }
+    // 吃到食物和移动在同一步
+    for _ in food_events.iter() {
+        let step = recorder.steps.len().saturating_sub(1);
+        recorder.eaten.push(step);
+    }
+}
+
+// 一局结束时，比之前的记录好就保存下来
+pub fn save_replay(
+    run: Res<CurrentRun>,
+    score: Res<Score>,
+    recorder: Res<RunRecorder>,
+    mut replays: ResMut<Replays>,
+) {
+    let Some(key) = replay_key(&run) else {
+        return;
+    };
+    let Some(replay) = recorder.replay(&run, score.0) else {
+        return;
+    };
+    if replays
+        .0
+        .get(&key)
+        .is_some_and(|best| !replay.is_better_than(best))
+    {
+        return;
+    }
+    info!("new personal best replay for {}", key);
+    replays.0.insert(key, replay);
+    storage::save(REPLAYS_FILE, &*replays);
+}
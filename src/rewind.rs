@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    common::{AppState, PlayState, Transition},
+    locale::{Localized, UiFonts},
+    mode::{CurrentRun, GameMode},
+    savegame::{GameRestore, GameSnapshot, SavedGame},
+    snake::SnakeMovedEvent,
+    theme::{Theme, ThemedText},
+};
+
+// 最多可以倒退的步数，大约 5 秒
+const REWIND_CAPACITY: usize = 34;
+// 倒退时每一步的间隔，比正常移动快
+const REWIND_STEP: f32 = 0.05;
+const REWIND_KEY: KeyCode = KeyCode::R;
+
+// 练习模式中最近每一步之后的状态
+#[derive(Resource)]
+pub struct RewindBuffer {
+    states: VecDeque<SavedGame>,
+    timer: Timer,
+    // 撞到后停住，等玩家倒退
+    crashed: bool,
+}
+
+impl Default for RewindBuffer {
+    fn default() -> Self {
+        Self {
+            states: VecDeque::with_capacity(REWIND_CAPACITY),
+            timer: Timer::from_seconds(REWIND_STEP, TimerMode::Repeating),
+            crashed: false,
+        }
+    }
+}
+
+impl RewindBuffer {
+    // 练习模式中撞到时不结束，停下来等待倒退
+    pub fn can_rewind(&self, run: &CurrentRun) -> bool {
+        run.mode == GameMode::Practice && !self.states.is_empty()
+    }
+
+    pub fn crash(&mut self) {
+        self.crashed = true;
+    }
+}
+
+#[derive(Component)]
+pub struct RewindHint;
+
+pub fn setup_rewind_hint(mut commands: Commands, fonts: Res<UiFonts>, theme: Res<Theme>) {
+    commands
+        .spawn(
+            TextBundle::from_section(
+                "Hold R to rewind",
+                TextStyle {
+                    font: fonts.bold.clone(),
+                    font_size: 20.0,
+                    color: theme.hud_label,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert((
+            RewindHint,
+            ThemedText::Hud,
+            Localized("hud.rewind".to_string()),
+            Visibility::INVISIBLE,
+        ));
+}
+
+pub fn reset_rewind(mut buffer: ResMut<RewindBuffer>) {
+    *buffer = RewindBuffer::default();
+}
+
+// 每走一步记录一次，上一帧的移动这一帧才完全生效
+pub fn record_rewind(
+    run: Res<CurrentRun>,
+    mut events: EventReader<SnakeMovedEvent>,
+    mut buffer: ResMut<RewindBuffer>,
+    snapshot: GameSnapshot,
+) {
+    if events.iter().count() == 0 || run.mode != GameMode::Practice {
+        return;
+    }
+    let Some(state) = snapshot.take() else {
+        return;
+    };
+    if buffer.states.len() == REWIND_CAPACITY {
+        buffer.states.pop_front();
+    }
+    buffer.states.push_back(state);
+}
+
+// 按住倒退键开始倒退
+pub fn start_rewind(
+    run: Res<CurrentRun>,
+    keyboard_input: Res<Input<KeyCode>>,
+    buffer: Res<RewindBuffer>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    if run.mode == GameMode::Practice
+        && keyboard_input.pressed(REWIND_KEY)
+        && !buffer.states.is_empty()
+    {
+        // 同一帧里已经撞到时忽略
+        app_state.transition(AppState::InGame(PlayState::Rewinding));
+    }
+}
+
+// 按住时一步一步恢复之前的状态，松开后继续游戏
+pub fn rewind_game(
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut buffer: ResMut<RewindBuffer>,
+    mut restore: GameRestore,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    if !keyboard_input.pressed(REWIND_KEY) {
+        if !buffer.crashed {
+            app_state.transition(AppState::InGame(PlayState::Running));
+        }
+        return;
+    }
+    if !buffer.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    // 至少保留最早的一个状态
+    let state = if buffer.states.len() > 1 {
+        buffer.states.pop_back()
+    } else {
+        buffer.states.back().cloned()
+    };
+    if let Some(state) = state {
+        restore.restore(state);
+        buffer.crashed = false;
+    }
+}
+
+pub fn update_rewind_hint(
+    run: Res<CurrentRun>,
+    app_state: Res<State<AppState>>,
+    mut query: Query<&mut Visibility, With<RewindHint>>,
+) {
+    let mut visibility = query.single_mut();
+    visibility.is_visible =
+        run.mode == GameMode::Practice && matches!(app_state.current(), AppState::InGame(_));
+}
@@ -0,0 +1,44 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::de::DeserializeOwned;
+
+// 从RON文件加载数据资源，例如主题
+pub struct RonAssetLoader<T> {
+    extensions: &'static [&'static str],
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> RonAssetLoader<T> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> AssetLoader for RonAssetLoader<T>
+where
+    T: TypeUuid + DeserializeOwned + Send + Sync + 'static,
+{
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let asset = ron::de::from_bytes::<T>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(asset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
@@ -0,0 +1,205 @@
+use bevy::{ecs::system::SystemParam, prelude::*, window::WindowCloseRequested};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{AppState, PlayState, Transition},
+    hazard::{spawn_hazard, Hazard, HazardState},
+    level::{spawn_level, Level, Portal, Wall},
+    mode::CurrentRun,
+    score::{HighScores, Record, RunStats, Score},
+    shrink::{Arena, ArenaState},
+    snake::{
+        spawn_food, spawn_snake, Direction, Food, FoodAssets, FoodKind, FoodTimer, Point, Snake,
+        SnakeAtlas,
+    },
+    storage,
+    theme::Theme,
+    time_attack::Countdown,
+};
+
+// 中途退出的一局，保存在数据目录，一局结束或者开始新的一局时删除
+const SAVE_FILE: &str = "savegame.ron";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedGame {
+    run: CurrentRun,
+    level: Level,
+    // 从蛇头到蛇尾
+    snake: Vec<Point>,
+    direction: Direction,
+    // 距离上一次移动的秒数
+    move_elapsed: f32,
+    food: Vec<Food>,
+    // 还在等待生成食物的计时
+    food_timers: Vec<f32>,
+    score: u32,
+    time: f32,
+    food_eaten: Vec<(FoodKind, u32)>,
+    countdown: Countdown,
+    arena: ArenaState,
+    hazards: Vec<HazardState>,
+}
+
+// 读取当前一局的状态
+#[derive(SystemParam)]
+pub struct GameSnapshot<'w, 's> {
+    run: Res<'w, CurrentRun>,
+    level: Res<'w, Level>,
+    score: Res<'w, Score>,
+    stats: Res<'w, RunStats>,
+    countdown: Res<'w, Countdown>,
+    arena: Res<'w, Arena>,
+    snake_query: Query<'w, 's, (&'static Snake, &'static Children)>,
+    point_query: Query<'w, 's, &'static Point>,
+    food_query: Query<'w, 's, &'static Food>,
+    timer_query: Query<'w, 's, &'static FoodTimer>,
+    hazard_query: Query<'w, 's, &'static Hazard>,
+}
+
+impl GameSnapshot<'_, '_> {
+    pub fn take(&self) -> Option<SavedGame> {
+        let (snake, children) = self.snake_query.iter().next()?;
+        let body: Vec<Point> = children
+            .iter()
+            .filter_map(|entity| self.point_query.get(*entity).ok().copied())
+            .collect();
+        if body.is_empty() {
+            return None;
+        }
+        Some(SavedGame {
+            run: self.run.clone(),
+            level: self.level.clone(),
+            snake: body,
+            direction: snake.move_direction,
+            move_elapsed: snake.move_timer.elapsed_secs(),
+            food: self.food_query.iter().copied().collect(),
+            food_timers: self
+                .timer_query
+                .iter()
+                .filter_map(FoodTimer::elapsed)
+                .collect(),
+            score: self.score.0,
+            time: self.stats.time,
+            food_eaten: self
+                .stats
+                .food_eaten
+                .iter()
+                .map(|(k, n)| (*k, *n))
+                .collect(),
+            countdown: self.countdown.clone(),
+            arena: self.arena.state(),
+            hazards: self.hazard_query.iter().map(Hazard::state).collect(),
+        })
+    }
+
+    fn save(&self) {
+        match self.take() {
+            Some(saved) => storage::save(SAVE_FILE, &saved),
+            None => warn!("no game to save"),
+        }
+    }
+}
+
+pub fn has_saved_game() -> bool {
+    storage::exists(SAVE_FILE)
+}
+
+// 暂停时保存，从暂停菜单回到主菜单后可以继续
+pub fn save_game(snapshot: GameSnapshot) {
+    snapshot.save();
+}
+
+// 游戏中直接关闭窗口时也保存
+pub fn save_on_close(
+    mut events: EventReader<WindowCloseRequested>,
+    app_state: Res<State<AppState>>,
+    snapshot: GameSnapshot,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+    if matches!(
+        app_state.current(),
+        AppState::InGame(PlayState::Running | PlayState::Paused)
+    ) {
+        snapshot.save();
+    }
+}
+
+pub fn delete_saved_game() {
+    storage::remove(SAVE_FILE);
+}
+
+// 把保存的状态恢复到场景中，去掉当前的蛇、食物和地图
+#[derive(SystemParam)]
+pub struct GameRestore<'w, 's> {
+    commands: Commands<'w, 's>,
+    food_assets: Res<'w, FoodAssets>,
+    atlas: Res<'w, SnakeAtlas>,
+    theme: Res<'w, Theme>,
+    high_scores: Res<'w, HighScores>,
+    run: ResMut<'w, CurrentRun>,
+    level: ResMut<'w, Level>,
+    score: ResMut<'w, Score>,
+    record: ResMut<'w, Record>,
+    stats: ResMut<'w, RunStats>,
+    countdown: ResMut<'w, Countdown>,
+    arena: ResMut<'w, Arena>,
+    snake_query: Query<'w, 's, Entity, With<Snake>>,
+    food_query: Query<'w, 's, Entity, Or<(With<Food>, With<FoodTimer>)>>,
+    tile_query: Query<'w, 's, Entity, Or<(With<Wall>, With<Portal>, With<Hazard>)>>,
+}
+
+impl GameRestore<'_, '_> {
+    pub fn restore(&mut self, saved: SavedGame) {
+        for entity in self.snake_query.iter() {
+            self.commands.entity(entity).despawn_recursive();
+        }
+        for entity in self.food_query.iter().chain(self.tile_query.iter()) {
+            self.commands.entity(entity).despawn();
+        }
+
+        *self.run = saved.run;
+        *self.level = saved.level;
+        spawn_level(&mut self.commands, &self.theme, &self.level);
+        for hazard in saved.hazards {
+            spawn_hazard(&mut self.commands, &self.theme, hazard);
+        }
+        spawn_snake(
+            &mut self.commands,
+            &self.atlas,
+            &saved.snake,
+            saved.direction,
+            saved.move_elapsed,
+        );
+        for food in saved.food {
+            spawn_food(&mut self.commands, &self.food_assets, food, false);
+        }
+        for elapsed in saved.food_timers {
+            self.commands.spawn(FoodTimer::with_elapsed(elapsed));
+        }
+
+        self.score.0 = saved.score;
+        self.record.0 = self.high_scores.best(&self.run.mode.table());
+        *self.stats = RunStats {
+            time: saved.time,
+            food_eaten: saved.food_eaten.into_iter().collect(),
+            ..default()
+        };
+        *self.countdown = saved.countdown;
+        self.arena.restore(&saved.arena);
+    }
+}
+
+// 继续保存的一局，恢复后先暂停，等玩家准备好再继续
+// 进入 Resuming 时还不能切换状态，所以在之后的第一帧运行，这时 reset_arena 已经运行过
+pub fn resume_game(mut restore: GameRestore, mut app_state: ResMut<State<AppState>>) {
+    let Some(saved) = storage::try_load::<SavedGame>(SAVE_FILE) else {
+        warn!("failed to load {}, starting a new game", SAVE_FILE);
+        app_state.transition(AppState::InGame(PlayState::Starting));
+        return;
+    };
+    info!("resuming saved game");
+    restore.restore(saved);
+    app_state.transition(AppState::InGame(PlayState::Paused));
+}
@@ -1,4 +1,16 @@
-use bevy::prelude::*;
+use std::collections::BTreeMap;
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::GameClock,
+    locale::{Localized, UiFonts},
+    mode::{CurrentRun, DailyResults},
+    snake::{FoodEatenEvent, FoodKind, Snake, SnakeDiedEvent},
+    storage,
+    theme::{Theme, ThemedText},
+};
 
 // 分数
 #[derive(Resource)]
@@ -12,10 +24,94 @@ pub struct Record(pub u32);
 #[derive(Component)]
 pub struct Recordboard;
 
-pub fn setup_score(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-) {
+// 各模式的最高分排行榜，从高到低，保存在数据目录
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct HighScores(BTreeMap<String, Vec<u32>>);
+
+const HIGH_SCORES_FILE: &str = "high_scores.ron";
+const MAX_HIGH_SCORES: usize = 10;
+
+impl HighScores {
+    pub fn best(&self, table: &str) -> u32 {
+        self.0
+            .get(table)
+            .and_then(|scores| scores.first().copied())
+            .unwrap_or(0)
+    }
+
+    pub fn top(&self, table: &str) -> &[u32] {
+        self.0.get(table).map_or(&[], |scores| &scores[..])
+    }
+
+    // 加入一局的得分并保存，返回名次（从 1 开始），没有进入排行榜时返回 None
+    pub fn submit(&mut self, table: &str, score: u32) -> Option<usize> {
+        let rank = self.insert(table, score);
+        storage::save(HIGH_SCORES_FILE, self);
+        rank
+    }
+
+    fn insert(&mut self, table: &str, score: u32) -> Option<usize> {
+        if score == 0 {
+            return None;
+        }
+        let scores = self.0.entry(table.to_string()).or_default();
+        let rank = scores.partition_point(|&s| s >= score);
+        if rank >= MAX_HIGH_SCORES {
+            return None;
+        }
+        scores.insert(rank, score);
+        scores.truncate(MAX_HIGH_SCORES);
+        Some(rank + 1)
+    }
+}
+
+// 本局的统计，显示在游戏结束界面
+#[derive(Resource, Default)]
+pub struct RunStats {
+    pub time: f32,
+    pub food_eaten: HashMap<FoodKind, u32>,
+    pub length: usize,
+    pub death: Option<SnakeDiedEvent>,
+    pub new_record: bool,
+    pub rank: Option<usize>,
+}
+
+// 分数和最高记录之间的一行，放限时、闯关目标等各模式自己的信息，显示的项依次排开
+#[derive(Resource)]
+pub struct HudRow(pub Entity);
+
+pub fn setup_hud_row(mut commands: Commands) {
+    let row = commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(12.0),
+                    left: Val::Px(220.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Row,
+                ..default()
+            },
+            ..default()
+        })
+        .id();
+    commands.insert_resource(HudRow(row));
+}
+
+// 行中的一项，隐藏时不占位置
+pub fn hud_item_style() -> Style {
+    Style {
+        display: Display::None,
+        margin: UiRect {
+            right: Val::Px(20.0),
+            ..default()
+        },
+        ..default()
+    }
+}
+
+pub fn setup_score(mut commands: Commands, fonts: Res<UiFonts>, theme: Res<Theme>) {
     // 分数
     commands
         .spawn(
@@ -23,32 +119,35 @@ pub fn setup_score(
                 TextSection::new(
                     "Score: ",
                     TextStyle {
-                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font: fonts.bold.clone(),
                         font_size: 30.0,
-                        color: Color::rgba(0.5, 0.5, 1.0, 0.5),
+                        color: theme.hud_label,
                     },
                 ),
                 TextSection::new(
                     "0",
                     TextStyle {
-                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                        font: fonts.mono.clone(),
                         font_size: 30.0,
-                        color: Color::rgba(1.0, 0.5, 0.5, 0.5),
+                        color: theme.hud_value,
                     },
                 ),
             ])
             .with_style(Style {
                 position_type: PositionType::Absolute,
                 position: UiRect {
-                    top: Val::Px(4.0),
+                    top: Val::Px(12.0),
                     left: Val::Px(10.0),
                     ..default()
                 },
-                
                 ..default()
             }),
         )
-        .insert(Scoreboard);
+        .insert((
+            Scoreboard,
+            ThemedText::Hud,
+            Localized("hud.score".to_string()),
+        ));
 
     //  最高记录
     commands
@@ -57,32 +156,35 @@ pub fn setup_score(
                 TextSection::new(
                     "Record: ",
                     TextStyle {
-                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font: fonts.bold.clone(),
                         font_size: 30.0,
-                        color: Color::rgba(0.5, 0.5, 1.0, 0.5),
+                        color: theme.hud_label,
                     },
                 ),
                 TextSection::new(
                     "0",
                     TextStyle {
-                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                        font: fonts.mono.clone(),
                         font_size: 30.0,
-                        color: Color::rgba(1.0, 0.5, 0.5, 0.5),
+                        color: theme.hud_value,
                     },
                 ),
             ])
             .with_style(Style {
                 position_type: PositionType::Absolute,
                 position: UiRect {
-                    top: Val::Px(28.0),
-                    left: Val::Px(10.0),
+                    top: Val::Px(12.0),
+                    right: Val::Px(10.0),
                     ..default()
                 },
                 ..default()
             }),
         )
-        .insert(Recordboard);
-
+        .insert((
+            Recordboard,
+            ThemedText::Hud,
+            Localized("hud.record".to_string()),
+        ));
 }
 
 pub fn update_scoreboard(score: Res<Score>, mut query: Query<&mut Text, With<Scoreboard>>) {
@@ -95,6 +197,61 @@ pub fn update_recordboard(lines: Res<Record>, mut query: Query<&mut Text, With<R
     text.sections[1].value = lines.0.to_string();
 }
 
-pub fn clear_score(mut score: ResMut<Score>) {
+pub fn clear_score(mut score: ResMut<Score>, mut stats: ResMut<RunStats>) {
     score.0 = 0;
+    *stats = RunStats::default();
+}
+
+pub fn setup_high_scores(mut commands: Commands, mut record: ResMut<Record>) {
+    let high_scores: HighScores = storage::load(HIGH_SCORES_FILE);
+    record.0 = high_scores.best(&CurrentRun::default().mode.table());
+    commands.insert_resource(high_scores);
+}
+
+// 开始新的一局时显示该模式的最高记录
+pub fn show_mode_record(
+    run: Res<CurrentRun>,
+    high_scores: Res<HighScores>,
+    mut record: ResMut<Record>,
+) {
+    record.0 = high_scores.best(&run.mode.table());
+}
+
+pub fn track_run_time(clock: Res<GameClock>, mut stats: ResMut<RunStats>) {
+    stats.time += clock.delta_seconds();
+}
+
+pub fn count_food(mut events: EventReader<FoodEatenEvent>, mut stats: ResMut<RunStats>) {
+    for event in events.iter() {
+        *stats.food_eaten.entry(event.kind).or_default() += 1;
+    }
+}
+
+// 死亡时记录本局结果并更新排行榜
+pub fn record_run(
+    mut events: EventReader<SnakeDiedEvent>,
+    score: Res<Score>,
+    mut record: ResMut<Record>,
+    mut high_scores: ResMut<HighScores>,
+    mut daily: ResMut<DailyResults>,
+    mut stats: ResMut<RunStats>,
+    run: Res<CurrentRun>,
+    snake_query: Query<&Children, With<Snake>>,
+) {
+    for event in events.iter() {
+        stats.death = Some(*event);
+        stats.length = snake_query.iter().map(|children| children.len()).sum();
+        // 不计入排行榜的一局也不算新纪录
+        if !run.counted {
+            continue;
+        }
+        if let Some(date) = &run.date {
+            daily.record(date, score.0);
+        }
+        stats.new_record = score.0 > record.0;
+        if stats.new_record {
+            record.0 = score.0;
+        }
+        stats.rank = high_scores.submit(&run.mode.table(), score.0);
+    }
 }
@@ -0,0 +1,194 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    time::Duration,
+};
+
+use crate::{
+    campaign::Campaign,
+    leaderboard::{ScoreEntry, Submission, GLOBAL_LIMIT},
+    mode::{today, GameMode},
+    verify::verify,
+};
+
+// 玩家名最多保留的字符数
+const MAX_PLAYER_LEN: usize = 16;
+// 一次最多返回的名次数
+const MAX_LIMIT: usize = 100;
+// 请求内容的最大字节数，大约能放下几个小时的操作记录
+const MAX_BODY_LEN: usize = 1 << 20;
+const READ_TIMEOUT: u64 = 5;
+
+// 排行榜服务器的参考实现，所有提交保存在一个 JSON 文件中
+//   POST /scores                  提交一局，内容为 Submission
+//   GET  /scores/<table>?limit=N  每个玩家在该模式的最好成绩，从高到低
+//                                 每日挑战按日期分开，例如 daily_2023-02-01
+// 提交的操作记录会重新模拟一遍，结果和分数不符时拒绝
+pub struct ScoreServer {
+    path: PathBuf,
+    submissions: Vec<Submission>,
+    campaign: Campaign,
+}
+
+impl ScoreServer {
+    // 读取之前保存的提交，文件不存在时从空开始
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let submissions = match fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        Ok(Self {
+            path,
+            submissions,
+            campaign: Campaign::default(),
+        })
+    }
+
+    // 依次处理请求，单个请求出错不影响之后的请求
+    pub fn run(mut self, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            if let Err(err) = stream.and_then(|stream| self.handle(stream)) {
+                eprintln!("request failed: {err}");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn submit(&mut self, mut submission: Submission) -> Result<(), String> {
+        let player: String = submission
+            .player
+            .trim()
+            .chars()
+            .take(MAX_PLAYER_LEN)
+            .collect();
+        if player.is_empty() {
+            return Err("missing player name".to_string());
+        }
+        if submission.replay.score == 0 {
+            return Err("nothing to submit".to_string());
+        }
+        // 每日挑战只接受当天（UTC）的
+        let replay = &submission.replay;
+        if replay.mode == GameMode::Daily && replay.date.as_deref() != Some(today().as_str()) {
+            return Err("not today's daily challenge".to_string());
+        }
+        // 每日挑战每个玩家只计当天第一次提交
+        let table = replay.table();
+        if replay.mode == GameMode::Daily
+            && self
+                .submissions
+                .iter()
+                .any(|other| other.player == player && other.replay.table() == table)
+        {
+            return Err("daily challenge already submitted".to_string());
+        }
+        verify(&submission.replay, &self.campaign).map_err(|err| err.to_string())?;
+        submission.player = player;
+        self.submissions.push(submission);
+        let text = serde_json::to_string(&self.submissions).map_err(|err| err.to_string())?;
+        fs::write(&self.path, text).map_err(|err| err.to_string())
+    }
+
+    pub fn top(&self, table: &str, limit: usize) -> Vec<ScoreEntry> {
+        let mut best: HashMap<&str, u32> = HashMap::new();
+        for submission in &self.submissions {
+            if submission.replay.table() != table {
+                continue;
+            }
+            let score = best.entry(&submission.player).or_default();
+            *score = (*score).max(submission.replay.score);
+        }
+        let mut entries: Vec<ScoreEntry> = best
+            .into_iter()
+            .map(|(player, score)| ScoreEntry {
+                player: player.to_string(),
+                score,
+            })
+            .collect();
+        entries.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.player.cmp(&b.player)));
+        entries.truncate(limit);
+        entries
+    }
+
+    fn handle(&mut self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(READ_TIMEOUT)))?;
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let target = parts.next().unwrap_or_default().to_string();
+
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header)?;
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+
+        let (status, body) = if length > MAX_BODY_LEN {
+            (413, "request too large".to_string())
+        } else {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body)?;
+            self.route(&method, &target, &body)
+        };
+        respond(&stream, status, &body)
+    }
+
+    fn route(&mut self, method: &str, target: &str, body: &[u8]) -> (u16, String) {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        if method == "POST" && path == "/scores" {
+            return match serde_json::from_slice(body) {
+                Ok(submission) => match self.submit(submission) {
+                    Ok(()) => (201, "{}".to_string()),
+                    Err(reason) => (400, reason),
+                },
+                Err(err) => (400, err.to_string()),
+            };
+        }
+        if let (Some(table), "GET") = (path.strip_prefix("/scores/"), method) {
+            let limit = query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("limit="))
+                .and_then(|limit| limit.parse().ok())
+                .unwrap_or(GLOBAL_LIMIT)
+                .min(MAX_LIMIT);
+            return match serde_json::to_string(&self.top(table, limit)) {
+                Ok(entries) => (200, entries),
+                Err(err) => (500, err.to_string()),
+            };
+        }
+        (404, "not found".to_string())
+    }
+}
+
+// 成功时返回 JSON，出错时返回原因
+fn respond(mut stream: &TcpStream, status: u16, body: &str) -> io::Result<()> {
+    let (reason, content_type) = match status {
+        200 => ("OK", "application/json"),
+        201 => ("Created", "application/json"),
+        400 => ("Bad Request", "text/plain"),
+        404 => ("Not Found", "text/plain"),
+        413 => ("Payload Too Large", "text/plain"),
+        _ => ("Internal Server Error", "text/plain"),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
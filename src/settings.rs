@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{locale::BUILTIN_LOCALES, storage, theme::BUILTIN_THEMES};
+
+const SETTINGS_FILE: &str = "settings.ron";
+
+// 游戏设置，修改后保存在数据目录
+#[derive(Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub smooth_movement: bool,
+    // 关闭后不显示粒子、震屏等动画
+    pub effects: bool,
+    pub show_grid: bool,
+    // 当前主题在 BUILTIN_THEMES 中的下标，按名称保存
+    #[serde(serialize_with = "save_theme", deserialize_with = "load_theme")]
+    pub theme: usize,
+    // 音量 0..=MAX_VOLUME
+    pub volume: u32,
+    // 当前语言在 BUILTIN_LOCALES 中的下标，按语言代码保存
+    #[serde(serialize_with = "save_language", deserialize_with = "load_language")]
+    pub language: usize,
+}
+
+pub const MAX_VOLUME: u32 = 10;
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            smooth_movement: false,
+            effects: true,
+            show_grid: true,
+            theme: 0,
+            volume: MAX_VOLUME,
+            language: 0,
+        }
+    }
+}
+
+// 保存名称而不是下标，内置列表调整顺序后仍然有效
+fn save_name<S: Serializer>(
+    names: &[(&str, &str)],
+    index: usize,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    names[index].0.serialize(serializer)
+}
+
+// 找不到的名称使用第一个
+fn load_name<'de, D: Deserializer<'de>>(
+    names: &[(&str, &str)],
+    deserializer: D,
+) -> Result<usize, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    Ok(names.iter().position(|(n, _)| *n == name).unwrap_or(0))
+}
+
+fn save_theme<S: Serializer>(theme: &usize, serializer: S) -> Result<S::Ok, S::Error> {
+    save_name(&BUILTIN_THEMES, *theme, serializer)
+}
+
+fn load_theme<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    load_name(&BUILTIN_THEMES, deserializer)
+}
+
+fn save_language<S: Serializer>(language: &usize, serializer: S) -> Result<S::Ok, S::Error> {
+    save_name(&BUILTIN_LOCALES, *language, serializer)
+}
+
+fn load_language<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    load_name(&BUILTIN_LOCALES, deserializer)
+}
+
+pub fn setup_settings(mut commands: Commands) {
+    commands.insert_resource(storage::load::<Settings>(SETTINGS_FILE));
+}
+
+// 设置菜单中可以修改的选项
+#[derive(Clone, Copy)]
+pub enum Setting {
+    SmoothMovement,
+    Effects,
+    Grid,
+    Theme,
+    Volume,
+    Language,
+}
+
+impl Setting {
+    pub const ALL: [Setting; 6] = [
+        Setting::SmoothMovement,
+        Setting::Effects,
+        Setting::Grid,
+        Setting::Theme,
+        Setting::Volume,
+        Setting::Language,
+    ];
+
+    // 字符串表中的 key
+    pub fn label(&self) -> &'static str {
+        match *self {
+            Setting::SmoothMovement => "settings.smooth_movement",
+            Setting::Effects => "settings.effects",
+            Setting::Grid => "settings.grid",
+            Setting::Theme => "settings.theme",
+            Setting::Volume => "settings.volume",
+            Setting::Language => "settings.language",
+        }
+    }
+
+    // 滑动条显示，其余为点击切换
+    pub fn is_slider(&self) -> bool {
+        matches!(self, Setting::Volume)
+    }
+}
+
+impl Settings {
+    pub fn change(&mut self, setting: Setting) {
+        match setting {
+            Setting::SmoothMovement => self.smooth_movement = !self.smooth_movement,
+            Setting::Effects => self.effects = !self.effects,
+            Setting::Grid => self.show_grid = !self.show_grid,
+            Setting::Theme => self.theme = (self.theme + 1) % BUILTIN_THEMES.len(),
+            Setting::Volume => self.volume = (self.volume + 1) % (MAX_VOLUME + 1),
+            Setting::Language => self.language = (self.language + 1) % BUILTIN_LOCALES.len(),
+        }
+        storage::save(SETTINGS_FILE, self);
+    }
+
+    pub fn adjust(&mut self, setting: Setting, step: i32) {
+        match setting {
+            Setting::Theme => self.theme = cycle(self.theme, step, BUILTIN_THEMES.len()),
+            Setting::Language => {
+                self.language = cycle(self.language, step, BUILTIN_LOCALES.len());
+            }
+            Setting::Volume => {
+                self.volume = self.volume.saturating_add_signed(step).min(MAX_VOLUME);
+            }
+            _ => {
+                self.change(setting);
+                return;
+            }
+        }
+        storage::save(SETTINGS_FILE, self);
+    }
+
+    // 滑动条的填充比例
+    pub fn fraction(&self, setting: Setting) -> f32 {
+        match setting {
+            Setting::Volume => self.volume as f32 / MAX_VOLUME as f32,
+            _ => 0.0,
+        }
+    }
+
+    pub fn playback(&self) -> PlaybackSettings {
+        PlaybackSettings::ONCE.with_volume(self.volume as f32 / MAX_VOLUME as f32)
+    }
+
+    // 当前值，可翻译的值返回字符串表中的 key
+    pub fn value_text(&self, setting: Setting) -> String {
+        let on_off = |on: bool| if on { "settings.on" } else { "settings.off" }.to_string();
+        match setting {
+            Setting::SmoothMovement => on_off(self.smooth_movement),
+            Setting::Effects => on_off(self.effects),
+            Setting::Grid => on_off(self.show_grid),
+            Setting::Theme => BUILTIN_THEMES[self.theme].1.to_string(),
+            Setting::Volume => format!("{}%", self.volume * 100 / MAX_VOLUME),
+            Setting::Language => BUILTIN_LOCALES[self.language].1.to_string(),
+        }
+    }
+}
+
+fn cycle(index: usize, step: i32, len: usize) -> usize {
+    (index as i32 + step).rem_euclid(len as i32) as usize
+}
@@ -0,0 +1,164 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::GameClock,
+    level::{spawn_wall, Level},
+    score::Score,
+    snake::{DeathCause, Food, FoodTimer, GameEnd, Point, Snake, SQUARE_SIZE, X, Y},
+    theme::Theme,
+};
+
+// 每隔多少秒收缩一圈，收缩前几秒开始闪烁提示
+pub const SHRINK_INTERVAL: f32 = 15.0;
+const WARNING_TIME: f32 = 3.0;
+// 最多收缩的圈数，留下 19x5 的空间
+pub const MAX_MARGIN: i32 = 6;
+
+#[derive(Resource, Default)]
+pub struct Arena {
+    timer: Timer,
+    // 不足一秒的存活时间，满一秒加一分
+    survived: f32,
+}
+
+// 存档中的收缩进度
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ArenaState {
+    elapsed: f32,
+    survived: f32,
+}
+
+impl Arena {
+    pub fn state(&self) -> ArenaState {
+        ArenaState {
+            elapsed: self.timer.elapsed_secs(),
+            survived: self.survived,
+        }
+    }
+
+    pub fn restore(&mut self, state: &ArenaState) {
+        self.timer
+            .set_elapsed(Duration::from_secs_f32(state.elapsed));
+        self.survived = state.survived;
+    }
+
+    // 距离下一次收缩还有多久，不再收缩时返回 None
+    pub fn remaining(&self, level: &Level) -> Option<Duration> {
+        (level.shrinking && level.margin < MAX_MARGIN).then(|| self.timer.remaining())
+    }
+}
+
+// 即将变成墙的格子
+#[derive(Component)]
+pub struct ShrinkWarning;
+
+pub fn reset_arena(
+    mut commands: Commands,
+    mut arena: ResMut<Arena>,
+    warning_query: Query<Entity, With<ShrinkWarning>>,
+) {
+    *arena = Arena {
+        timer: Timer::from_seconds(SHRINK_INTERVAL, TimerMode::Repeating),
+        survived: 0.0,
+    };
+    for entity in &warning_query {
+        commands.entity(entity).despawn();
+    }
+}
+
+// 下一次收缩时变成墙的一圈格子
+pub fn next_ring(level: &Level) -> Vec<Point> {
+    let mut ring = Vec::new();
+    for x in -X..=X {
+        for y in -Y..=Y {
+            let point = Point::new(x, y);
+            if level.is_inside(&point, level.margin) && !level.is_inside(&point, level.margin + 1) {
+                ring.push(point);
+            }
+        }
+    }
+    ring
+}
+
+pub fn shrink_arena(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    theme: Res<Theme>,
+    mut arena: ResMut<Arena>,
+    mut level: ResMut<Level>,
+    mut score: ResMut<Score>,
+    warning_query: Query<Entity, With<ShrinkWarning>>,
+    snake_query: Query<&Children, With<Snake>>,
+    point_query: Query<&Point>,
+    food_query: Query<(Entity, &Food)>,
+    mut game_end: GameEnd,
+) {
+    if !level.shrinking {
+        return;
+    }
+    arena.survived += clock.delta_seconds();
+    while arena.survived >= 1.0 {
+        arena.survived -= 1.0;
+        score.0 += 1;
+    }
+    if level.margin >= MAX_MARGIN {
+        return;
+    }
+
+    arena.timer.tick(clock.delta());
+    if arena.timer.remaining_secs() <= WARNING_TIME && warning_query.is_empty() {
+        for point in next_ring(&level) {
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: theme.border,
+                        custom_size: Some(Vec2::splat(SQUARE_SIZE)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(point.translation()),
+                    ..default()
+                },
+                ShrinkWarning,
+            ));
+        }
+    }
+    if !arena.timer.just_finished() {
+        return;
+    }
+
+    let ring = next_ring(&level);
+    level.margin += 1;
+    for entity in &warning_query {
+        commands.entity(entity).despawn();
+    }
+    for point in &ring {
+        spawn_wall(&mut commands, &theme, *point);
+    }
+    // 墙里的食物换个位置重新生成
+    for (entity, food) in &food_query {
+        if ring.contains(&food.point) {
+            commands.entity(entity).despawn_recursive();
+            commands.spawn(FoodTimer::default());
+        }
+    }
+    // 被墙压到就死了
+    let crushed = snake_query
+        .iter()
+        .flat_map(|children| children.iter())
+        .filter_map(|entity| point_query.get(*entity).ok())
+        .find(|point| ring.contains(point));
+    if let Some(point) = crushed {
+        game_end.die(*point, DeathCause::Wall);
+    }
+}
+
+// 提示的格子闪烁
+pub fn blink_warnings(time: Res<Time>, mut query: Query<&mut Sprite, With<ShrinkWarning>>) {
+    let alpha = 0.5 + 0.4 * (time.elapsed_seconds() * 10.0).sin();
+    for mut sprite in &mut query {
+        sprite.color.set_a(alpha);
+    }
+}
@@ -1,22 +1,41 @@
-use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use std::time::Duration;
+
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
 use rand::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    common::{AppState, GameAudios, GameState, HEIGHT, WIDTH},
-    score::{Record, Score},
+    clock::GameClock,
+    common::{AppState, GameAudios, PlayState, Transition, HEIGHT, WIDTH},
+    effects::{PopIn, Scatter},
+    hazard::Hazard,
+    level::Level,
+    mode::{CurrentRun, GameMode},
+    rewind::RewindBuffer,
+    score::Score,
+    settings::Settings,
+    theme::Theme,
 };
 
 pub const SQUARE_SIZE: f32 = 30.0;
+// 每走一格的秒数
+pub const MOVE_INTERVAL: f32 = 0.15;
+// 吃掉食物后过多少秒生成新的食物
+pub const FOOD_DELAY: f32 = 1.0;
 pub const X: i32 = (WIDTH / 2.0 / SQUARE_SIZE) as i32;
 pub const Y: i32 = (HEIGHT / 2.0 / SQUARE_SIZE) as i32;
 
-#[derive(Component, Clone, Copy)]
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Point {
-    x: i32,
-    y: i32,
+    pub x: i32,
+    pub y: i32,
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Direction {
     Up,
     Right,
@@ -24,24 +43,64 @@ pub enum Direction {
     Left,
 }
 
-impl Point {
-    fn random() -> Self {
-        let square_width: i32 = (WIDTH / SQUARE_SIZE / 2.0) as i32;
-        let square_height: i32 = (HEIGHT / SQUARE_SIZE / 2.0) as i32;
+impl Direction {
+    // 贴图默认朝右，按方向旋转
+    pub fn angle(&self) -> f32 {
+        match *self {
+            Direction::Right => 0.0,
+            Direction::Up => std::f32::consts::FRAC_PI_2,
+            Direction::Left => std::f32::consts::PI,
+            Direction::Down => -std::f32::consts::FRAC_PI_2,
+        }
+    }
+    // 逆时针旋转90度后的方向
+    pub fn turn_left(&self) -> Self {
+        match *self {
+            Direction::Right => Direction::Up,
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+        }
+    }
+    pub fn opposite(&self) -> Self {
+        self.turn_left().turn_left()
+    }
+    // 两个相邻格子之间的方向
+    fn between(from: &Point, to: &Point) -> Option<Self> {
+        match (to.x - from.x, to.y - from.y) {
+            (1, 0) => Some(Direction::Right),
+            (-1, 0) => Some(Direction::Left),
+            (0, 1) => Some(Direction::Up),
+            (0, -1) => Some(Direction::Down),
+            _ => None,
+        }
+    }
+}
 
-        let mut rng = thread_rng();
-        let x = rng.gen_range(-square_width..square_width);
-        let y = rng.gen_range(-square_height..square_height);
+impl Point {
+    pub const fn new(x: i32, y: i32) -> Self {
         Self { x, y }
     }
-    fn translation(&self) -> Vec3 {
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let x = rng.gen_range(-X..=X);
+        let y = rng.gen_range(-Y..=Y);
+        Self { x, y }
+    }
+    pub fn in_bounds(&self) -> bool {
+        (-X..=X).contains(&self.x) && (-Y..=Y).contains(&self.y)
+    }
+    pub fn translation(&self) -> Vec3 {
         Vec3 {
             x: (self.x as f32) * SQUARE_SIZE,
             y: (self.y as f32) * SQUARE_SIZE,
             z: 0.0,
         }
     }
-    fn from_direction(&mut self, direction: &Direction) -> Self {
+    // 相同或相邻的格子，之间可以插值
+    fn is_adjacent(&self, other: &Point) -> bool {
+        (self.x - other.x).abs() + (self.y - other.y).abs() <= 1
+    }
+    pub fn next(&self, direction: &Direction) -> Self {
         let mut x = self.x;
         let mut y = self.y;
         match *direction {
@@ -54,24 +113,263 @@ impl Point {
     }
 }
 
-#[derive(Component)]
-pub struct Food(Point);
+impl std::fmt::Display for Point {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+// 食物种类，金色食物较少出现但分数更高
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FoodKind {
+    Normal,
+    Golden,
+    // 限时模式中增加剩余时间
+    Clock,
+}
+
+impl FoodKind {
+    pub const ALL: [FoodKind; 3] = [FoodKind::Normal, FoodKind::Golden, FoodKind::Clock];
+
+    fn random(rng: &mut impl Rng, time_bonus: bool) -> Self {
+        let roll: f64 = rng.gen();
+        if roll < 0.1 {
+            FoodKind::Golden
+        } else if time_bonus && roll < 0.25 {
+            FoodKind::Clock
+        } else {
+            FoodKind::Normal
+        }
+    }
+
+    pub fn score(&self) -> u32 {
+        match *self {
+            FoodKind::Normal => 1,
+            FoodKind::Golden => 3,
+            FoodKind::Clock => 1,
+        }
+    }
+
+    pub fn color(&self, theme: &Theme) -> Color {
+        match *self {
+            FoodKind::Normal => theme.food,
+            FoodKind::Golden => theme.bonus_food,
+            FoodKind::Clock => theme.clock_food,
+        }
+    }
+
+    // 字符串表中的 key
+    pub fn label(&self) -> &'static str {
+        match *self {
+            FoodKind::Normal => "summary.food.normal",
+            FoodKind::Golden => "summary.food.golden",
+            FoodKind::Clock => "summary.food.clock",
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, Serialize, Deserialize)]
+pub struct Food {
+    pub point: Point,
+    pub kind: FoodKind,
+}
 
 #[derive(Component)]
 pub struct FoodTimer(Timer);
 
-#[derive(Component)]
-pub struct BodyIncreaseTimer(Timer);
+impl Default for FoodTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(FOOD_DELAY, TimerMode::Once))
+    }
+}
+
+impl FoodTimer {
+    // 还在等待的计时，已经暂停或生成过食物的返回 None
+    pub fn elapsed(&self) -> Option<f32> {
+        (!self.0.paused() && !self.0.finished()).then(|| self.0.elapsed_secs())
+    }
+
+    // 距离生成食物还有多久，同样只算还在等待的计时
+    pub fn remaining(&self) -> Option<Duration> {
+        (!self.0.paused() && !self.0.finished()).then(|| self.0.remaining())
+    }
+
+    pub fn with_elapsed(elapsed: f32) -> Self {
+        let mut timer = Self::default();
+        timer.0.set_elapsed(Duration::from_secs_f32(elapsed));
+        timer
+    }
+}
+
+// 吃到食物
+pub struct FoodEatenEvent {
+    pub point: Point,
+    pub kind: FoodKind,
+}
+
+// 蛇头移动到新的格子
+pub struct SnakeMovedEvent {
+    pub direction: Direction,
+    pub from: Point,
+    pub to: Point,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeathCause {
+    Wall,
+    SelfHit,
+    // 限时模式时间用完
+    TimeUp,
+    // 碰到移动的障碍
+    Hazard,
+}
+
+// 撞墙或者撞到自己，point 为撞击时蛇头的位置
+#[derive(Clone, Copy)]
+pub struct SnakeDiedEvent {
+    pub point: Point,
+    pub cause: DeathCause,
+}
+
+// 调试控制台打开的无敌，撞到时停住不结束
+#[derive(Resource, Default)]
+pub struct Invincible(pub bool);
+
+// 结束这一局：播放死亡音效，进入死亡动画，之后显示游戏结束界面
+#[derive(SystemParam)]
+pub struct GameEnd<'w, 's> {
+    app_state: ResMut<'w, State<AppState>>,
+    audio: Res<'w, Audio>,
+    game_audios: Res<'w, GameAudios>,
+    settings: Res<'w, Settings>,
+    events: EventWriter<'w, 's, SnakeDiedEvent>,
+    run: Res<'w, CurrentRun>,
+    rewind: ResMut<'w, RewindBuffer>,
+    invincible: Res<'w, Invincible>,
+}
+
+impl GameEnd<'_, '_> {
+    // 同一帧里已经结束或者无敌时返回 false
+    pub fn die(&mut self, point: Point, cause: DeathCause) -> bool {
+        if self.invincible.0 {
+            return false;
+        }
+        // 练习模式中停下来等待倒退
+        if self.rewind.can_rewind(&self.run) {
+            if !self
+                .app_state
+                .transition(AppState::InGame(PlayState::Rewinding))
+            {
+                return false;
+            }
+            self.rewind.crash();
+            self.audio
+                .play_with_settings(self.game_audios.die.clone(), self.settings.playback());
+            return true;
+        }
+        if !self.app_state.transition(AppState::Dying) {
+            return false;
+        }
+        self.audio
+            .play_with_settings(self.game_audios.die.clone(), self.settings.playback());
+        self.events.send(SnakeDiedEvent { point, cause });
+        true
+    }
+
+    // 完成闯关目标，进入过关界面
+    pub fn complete(&mut self) -> bool {
+        if !self.app_state.transition(AppState::LevelComplete) {
+            return false;
+        }
+        self.audio
+            .play_with_settings(self.game_audios.eat.clone(), self.settings.playback());
+        true
+    }
+}
 
 #[derive(Component)]
 pub struct Snake {
-    move_timer: Timer,
-    move_direction: Direction,
+    pub move_timer: Timer,
+    pub move_direction: Direction,
+    // 上一次移动前各节身体的位置，用于平滑移动插值
+    trail: Vec<Point>,
 }
 
-pub fn setup_snake(mut commands: Commands) {
-    commands.spawn(FoodTimer(Timer::from_seconds(1.0, TimerMode::Once)));
+// 蛇身贴图：蛇头、直身、拐角、蛇尾
+#[derive(Resource)]
+pub struct SnakeAtlas(Handle<TextureAtlas>);
+
+const HEAD_INDEX: usize = 0;
+const BODY_INDEX: usize = 1;
+const CORNER_INDEX: usize = 2;
+const TAIL_INDEX: usize = 3;
 
+pub fn setup_snake_atlas(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let texture = asset_server.load("textures/snake.png");
+    let atlas = TextureAtlas::from_grid(texture, Vec2::new(32.0, 32.0), 4, 1, None, None);
+    commands.insert_resource(SnakeAtlas(texture_atlases.add(atlas)));
+}
+
+fn spawn_segment(commands: &mut Commands, atlas: &SnakeAtlas, point: Point) -> Entity {
+    commands
+        .spawn((
+            SpriteSheetBundle {
+                texture_atlas: atlas.0.clone(),
+                transform: Transform {
+                    translation: point.translation(),
+                    ..default()
+                },
+                sprite: TextureAtlasSprite {
+                    index: BODY_INDEX,
+                    custom_size: Some(Vec2::new(SQUARE_SIZE, SQUARE_SIZE)),
+                    ..default()
+                },
+                ..default()
+            },
+            point,
+        ))
+        .id()
+}
+
+// 在蛇尾叠加 n 节，之后每走一步展开一节
+pub fn grow_snake(
+    commands: &mut Commands,
+    atlas: &SnakeAtlas,
+    parent: Entity,
+    tail: Point,
+    n: usize,
+) {
+    for _ in 0..n {
+        let segment = spawn_segment(commands, atlas, tail);
+        commands.entity(parent).add_child(segment);
+    }
+}
+
+pub fn setup_snake(mut commands: Commands, atlas: Res<SnakeAtlas>, level: Res<Level>) {
+    commands.spawn(FoodTimer::default());
+    spawn_snake(
+        &mut commands,
+        &atlas,
+        &level.snake_body(),
+        level.direction,
+        0.0,
+    );
+}
+
+// body 从蛇头开始，elapsed 为距离上一次移动的秒数
+pub fn spawn_snake(
+    commands: &mut Commands,
+    atlas: &SnakeAtlas,
+    body: &[Point],
+    direction: Direction,
+    elapsed: f32,
+) {
+    let mut move_timer = Timer::from_seconds(MOVE_INTERVAL, TimerMode::Repeating);
+    move_timer.set_elapsed(Duration::from_secs_f32(elapsed));
     let parent = commands
         .spawn((
             SpriteBundle {
@@ -86,105 +384,157 @@ pub fn setup_snake(mut commands: Commands) {
                 ..default()
             },
             Snake {
-                move_timer: Timer::from_seconds(0.15, TimerMode::Repeating),
-                move_direction: Direction::Right,
+                move_timer,
+                move_direction: direction,
+                trail: Vec::new(),
             },
         ))
         .id();
 
-    let init_points: Vec<Point> = vec![
-        Point { x: -5, y: 0 },
-        Point { x: -6, y: 0 },
-        Point { x: -7, y: 0 },
-    ];
-
-    for point in init_points {
-        let children = commands
-            .spawn((
-                SpriteBundle {
-                    transform: Transform {
-                        translation: point.translation(),
-                        ..default()
-                    },
-                    sprite: Sprite {
-                        color: Color::rgb(0.5, 1.0, 0.5),
-                        custom_size: Some(Vec2::new(SQUARE_SIZE, SQUARE_SIZE)),
-                        ..default()
-                    },
-                    ..default()
-                },
-                point,
-            ))
-            .id();
+    for point in body {
+        let children = spawn_segment(commands, atlas, *point);
         commands.entity(parent).add_child(children);
     }
 }
 
 pub fn generate_food(
     mut commonds: Commands,
-    mut query: Query<&mut FoodTimer>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    point_query: Query<&mut Point, With<Sprite>>,
-    time: Res<Time>,
+    mut query: Query<(Entity, &mut FoodTimer)>,
+    food_assets: Res<FoodAssets>,
+    point_query: Query<&Point>,
+    hazard_query: Query<&Hazard>,
+    clock: Res<GameClock>,
+    settings: Res<Settings>,
+    level: Res<Level>,
+    mut run: ResMut<CurrentRun>,
 ) {
-    for mut timer in &mut query {
-        if timer.0.tick(time.delta()).just_finished() {
-            let square = new_food(&point_query);
-
-            commonds.spawn((
-                MaterialMesh2dBundle {
-                    mesh: meshes
-                        .add(shape::Circle::new(SQUARE_SIZE / 3.0).into())
-                        .into(),
-                    material: materials.add(ColorMaterial::from(Color::RED)),
-                    transform: Transform::from_translation(square.0.translation()),
-                    ..default()
-                },
-                square,
-            ));
+    for (entity, mut timer) in &mut query {
+        if timer.0.tick(clock.delta()).just_finished() {
+            commonds.entity(entity).despawn();
+            let time_bonus = matches!(run.mode, GameMode::TimeAttack(_));
+            let is_taken = |point: &Point| {
+                point_query.iter().any(|other| other == point)
+                    || hazard_query.iter().any(|hazard| hazard.point == *point)
+            };
+            let Some(food) = place_food(&level, &mut run.food_rng(), time_bonus, is_taken) else {
+                // 没有空位时稍后再试
+                commonds.spawn(FoodTimer::default());
+                continue;
+            };
+
+            spawn_food(&mut commonds, &food_assets, food, settings.effects);
         }
     }
 }
 
-// 生成一个不和蛇身重叠的food
-fn new_food(query: &Query<&mut Point, With<Sprite>>) -> Food {
-    let square = Food(Point::random());
-    let mut is_in_snake_body = false;
-    query.for_each(|point| {
-        if point.x == square.0.x && point.y == square.0.y {
-            is_in_snake_body = true;
+// 所有食物共用一个网格，同一种食物共用一个材质，生成食物时不再新建资源
+#[derive(Resource)]
+pub struct FoodAssets {
+    mesh: Mesh2dHandle,
+    // 按 FoodKind::ALL 的顺序
+    materials: [Handle<ColorMaterial>; 3],
+}
+
+impl FoodAssets {
+    fn material(&self, kind: FoodKind) -> Handle<ColorMaterial> {
+        self.materials[kind as usize].clone()
+    }
+
+    // 切换主题时更新所有食物的颜色
+    pub fn apply_theme(&self, materials: &mut Assets<ColorMaterial>, theme: &Theme) {
+        for kind in FoodKind::ALL {
+            if let Some(material) = materials.get_mut(&self.material(kind)) {
+                material.color = kind.color(theme);
+            }
         }
+    }
+}
+
+pub fn setup_food_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    theme: Res<Theme>,
+) {
+    commands.insert_resource(FoodAssets {
+        mesh: meshes
+            .add(shape::Circle::new(SQUARE_SIZE / 3.0).into())
+            .into(),
+        materials: FoodKind::ALL.map(|kind| materials.add(ColorMaterial::from(kind.color(&theme)))),
     });
-    if is_in_snake_body {
-        return new_food(query);
+}
+
+pub fn spawn_food(commands: &mut Commands, assets: &FoodAssets, food: Food, pop_in: bool) {
+    // 弹出动画从零开始放大
+    let scale = if pop_in { Vec3::ZERO } else { Vec3::ONE };
+    let mut entity = commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: assets.mesh.clone(),
+            material: assets.material(food.kind),
+            transform: Transform::from_translation(food.point.translation()).with_scale(scale),
+            ..default()
+        },
+        food,
+    ));
+    if pop_in {
+        entity.insert(PopIn(Timer::from_seconds(0.3, TimerMode::Once)));
+    }
+}
+
+// 生成一个不和墙、传送门以及 is_taken 的格子（蛇身、障碍）重叠的food
+// 关卡指定了食物区域时只在区域内生成
+pub fn place_food(
+    level: &Level,
+    rng: &mut impl Rng,
+    time_bonus: bool,
+    is_taken: impl Fn(&Point) -> bool,
+) -> Option<Food> {
+    // 几乎没有空位时放弃
+    for _ in 0..1000 {
+        let point = match level.food_zones.choose(rng) {
+            Some(point) => *point,
+            None => Point::random(rng),
+        };
+        let square = Food {
+            point,
+            kind: FoodKind::random(rng, time_bonus),
+        };
+        let is_in_snake_body = level.is_blocked(&square.point)
+            || level.is_doomed(&square.point)
+            || level.portal_exit(&square.point).is_some()
+            || is_taken(&square.point);
+        if !is_in_snake_body {
+            return Some(square);
+        }
     }
-    return square;
+    None
 }
 
 pub fn move_snake(
     mut commands: Commands,
-    time: Res<Time>,
+    clock: Res<GameClock>,
     mut parents_query: Query<(Entity, &Children, &mut Snake), With<Sprite>>,
-    mut transform_query: Query<&mut Transform, With<Sprite>>,
-    mut point_query: Query<&mut Point, With<Sprite>>,
+    mut point_query: Query<&mut Point>,
     food_query: Query<(Entity, &mut Food)>,
-    mut app_state: ResMut<State<AppState>>,
-    mut game_state: ResMut<State<GameState>>,
+    mut game_end: GameEnd,
     mut score: ResMut<Score>,
-    mut record: ResMut<Record>,
     audio: Res<Audio>,
     game_audios: Res<GameAudios>,
+    atlas: Res<SnakeAtlas>,
+    settings: Res<Settings>,
+    level: Res<Level>,
+    mut snake_moved_events: EventWriter<SnakeMovedEvent>,
+    mut food_eaten_events: EventWriter<FoodEatenEvent>,
 ) {
     for (parent, children, mut snake) in &mut parents_query {
-        if snake.move_timer.tick(time.delta()).just_finished() {
+        if snake.move_timer.tick(clock.delta()).just_finished() {
             let mut is_hit_wall = false;
             let mut is_hit_self = false;
-            if let Ok(mut head) = point_query.get_mut(children[0]) {
-                // 蛇头的下一个位置
-                let new_point = head.from_direction(&snake.move_direction);
+            if let Ok(&head) = point_query.get(children[0]) {
+                // 蛇头的下一个位置，进入传送门时从配对的传送门出来，方向不变
+                let new_point = level.step(&head, &snake.move_direction);
                 // 检查是否游戏失败 --- 撞墙或者撞到自己
-                if new_point.x < -X || new_point.x > X || new_point.y < -Y || new_point.y > Y {
+                if level.is_blocked(&new_point) {
                     is_hit_wall = true;
                 }
                 for entity in children {
@@ -194,67 +544,56 @@ pub fn move_snake(
                         }
                     }
                 }
-                if (is_hit_wall || is_hit_self) && app_state.current().clone() != AppState::GameOver
-                {
-                    // 更新得分最高记录
-                    if score.0 > record.0 {
-                        record.0 = score.0;
+                if is_hit_wall || is_hit_self {
+                    let cause = if is_hit_wall {
+                        DeathCause::Wall
+                    } else {
+                        DeathCause::SelfHit
+                    };
+                    if game_end.die(head, cause) {
+                        snake.trail.clear();
                     }
 
-                    app_state.set(AppState::GameOver).unwrap();
-                    game_state.set(GameState::Quitted).unwrap();
-                    audio.play(game_audios.die.clone());
-
                     return;
                 }
 
-                let mut is_eat_food = false;
+                snake.trail = children
+                    .iter()
+                    .filter_map(|entity| point_query.get(*entity).ok().copied())
+                    .collect();
+                snake_moved_events.send(SnakeMovedEvent {
+                    direction: snake.move_direction,
+                    from: head,
+                    to: new_point,
+                });
+
+                let mut eaten = None;
                 for (food_entity, food) in &food_query {
-                    if food.0.x == new_point.x && food.0.y == new_point.y {
-                        is_eat_food = true;
+                    if food.point.x == new_point.x && food.point.y == new_point.y {
+                        eaten = Some(food.kind);
                         commands.entity(food_entity).despawn_recursive();
                     }
                 }
 
-                if is_eat_food {
-                    let new_child = commands
-                        .spawn((
-                            SpriteBundle {
-                                transform: Transform {
-                                    translation: new_point.translation(),
-                                    ..default()
-                                },
-                                sprite: Sprite {
-                                    color: Color::rgb(0.5, 1.0, 0.5),
-                                    custom_size: Some(Vec2::new(SQUARE_SIZE, SQUARE_SIZE)),
-                                    ..default()
-                                },
-                                ..default()
-                            },
-                            new_point,
-                        ))
-                        .id();
-                    commands.entity(parent).insert_children(0, &vec![new_child]);
-                    commands.spawn(FoodTimer(Timer::from_seconds(1.0, TimerMode::Once)));
-                    audio.play(game_audios.eat.clone());
-
-                    score.0 += 1;
+                if let Some(kind) = eaten {
+                    let new_child = spawn_segment(&mut commands, &atlas, new_point);
+                    commands.entity(parent).insert_children(0, &[new_child]);
+                    commands.spawn(FoodTimer::default());
+                    audio.play_with_settings(game_audios.eat.clone(), settings.playback());
+                    food_eaten_events.send(FoodEatenEvent {
+                        point: new_point,
+                        kind,
+                    });
+
+                    score.0 += kind.score();
                 } else {
                     let tail_entity = children[children.len() - 1];
                     if let Ok(mut point) = point_query.get_mut(tail_entity) {
                         point.x = new_point.x;
                         point.y = new_point.y;
-                        let translation = point.translation();
+                        commands.entity(parent).remove_children(&[tail_entity]);
 
-                        if let Ok(mut transform) = transform_query.get_mut(tail_entity) {
-                            transform.translation.x = translation.x;
-                            transform.translation.y = translation.y;
-                        }
-                        commands.entity(parent).remove_children(&vec![tail_entity]);
-
-                        commands
-                            .entity(parent)
-                            .insert_children(0, &vec![tail_entity]);
+                        commands.entity(parent).insert_children(0, &[tail_entity]);
                     }
                 }
             }
@@ -262,11 +601,82 @@ pub fn move_snake(
     }
 }
 
+// 根据前后相邻的身体格子选择贴图、朝向和渐变颜色
+// 平滑模式下在上一次和当前的格子之间插值，逻辑上仍按格子移动
+pub fn render_snake(
+    snake_query: Query<(&Snake, &Children)>,
+    mut segment_query: Query<(&Point, &mut Transform, &mut TextureAtlasSprite), Without<Scatter>>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+) {
+    for (snake, children) in &snake_query {
+        let segments: Vec<(Entity, Point)> = children
+            .iter()
+            .filter_map(|entity| {
+                segment_query
+                    .get(*entity)
+                    .ok()
+                    .map(|(point, _, _)| (*entity, *point))
+            })
+            .collect();
+        let len = segments.len();
+        let progress = snake.move_timer.percent();
+
+        for (i, (entity, point)) in segments.iter().enumerate() {
+            // 指向蛇头方向和蛇尾方向的相邻格子
+            let to_front = i
+                .checked_sub(1)
+                .and_then(|j| Direction::between(point, &segments[j].1));
+            let to_back = segments
+                .get(i + 1)
+                .and_then(|(_, next)| Direction::between(point, next));
+
+            let (index, direction) = if i == 0 {
+                (HEAD_INDEX, snake.move_direction)
+            } else {
+                match (to_front, to_back) {
+                    (Some(front), None) => (TAIL_INDEX, front),
+                    (Some(front), Some(back)) if front.turn_left() == back => (CORNER_INDEX, front),
+                    (Some(front), Some(back)) if back.turn_left() == front => (CORNER_INDEX, back),
+                    (Some(front), _) => (BODY_INDEX, front),
+                    (None, Some(back)) => (BODY_INDEX, back),
+                    (None, None) => (BODY_INDEX, Direction::Right),
+                }
+            };
+
+            if let Ok((_, mut transform, mut sprite)) = segment_query.get_mut(*entity) {
+                sprite.index = index;
+                sprite.color = lerp_color(
+                    theme.snake_head,
+                    theme.snake_tail,
+                    i as f32 / len.max(2) as f32,
+                );
+                transform.rotation = Quat::from_rotation_z(direction.angle());
+                transform.translation = match snake.trail.get(i) {
+                    Some(from) if settings.smooth_movement && from.is_adjacent(point) => {
+                        from.translation().lerp(point.translation(), progress)
+                    }
+                    _ => point.translation(),
+                };
+            }
+        }
+    }
+}
+
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    Color::rgb(
+        from.r() + (to.r() - from.r()) * t,
+        from.g() + (to.g() - from.g()) * t,
+        from.b() + (to.b() - from.b()) * t,
+    )
+}
+
 pub fn contral_snake(
     keyboard_input: ResMut<Input<KeyCode>>,
     mut snake_query: Query<&mut Snake, With<Sprite>>,
     audio: Res<Audio>,
     game_audios: Res<GameAudios>,
+    settings: Res<Settings>,
 ) {
     for mut snake in &mut snake_query {
         if keyboard_input.pressed(KeyCode::Up)
@@ -274,63 +684,57 @@ pub fn contral_snake(
             && snake.move_direction != Direction::Up
         {
             snake.move_direction = Direction::Up;
-            audio.play(game_audios.up.clone());
+            audio.play_with_settings(game_audios.up.clone(), settings.playback());
         } else if keyboard_input.pressed(KeyCode::Down)
             && snake.move_direction != Direction::Up
             && snake.move_direction != Direction::Down
         {
             snake.move_direction = Direction::Down;
-            audio.play(game_audios.down.clone());
+            audio.play_with_settings(game_audios.down.clone(), settings.playback());
         } else if keyboard_input.pressed(KeyCode::Left)
             && snake.move_direction != Direction::Right
             && snake.move_direction != Direction::Left
         {
             snake.move_direction = Direction::Left;
-            audio.play(game_audios.left.clone());
+            audio.play_with_settings(game_audios.left.clone(), settings.playback());
         } else if keyboard_input.pressed(KeyCode::Right)
             && snake.move_direction != Direction::Left
             && snake.move_direction != Direction::Right
         {
             snake.move_direction = Direction::Right;
-            audio.play(game_audios.right.clone());
+            audio.play_with_settings(game_audios.right.clone(), settings.playback());
         }
     }
 }
 
+// 空格、Escape 或手柄 Start 键暂停，在暂停菜单中继续
 pub fn pause_game(
-    mut game_state: ResMut<State<GameState>>,
-    mut keyboard_input: ResMut<Input<KeyCode>>,
-    mut snake_query: Query<&mut Snake, With<Sprite>>,
+    mut app_state: ResMut<State<AppState>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_input: Res<Input<GamepadButton>>,
 ) {
-    for mut snake in &mut snake_query {
-        if keyboard_input.pressed(KeyCode::Space) {
-            if game_state.current().clone() == GameState::Pause {
-                snake.move_timer.unpause();
-                game_state.set(GameState::Playing).unwrap();
-            } else {
-                snake.move_timer.pause();
-                game_state.set(GameState::Pause).unwrap();
-            }
-            keyboard_input.reset(KeyCode::Space);
-        }
+    let start_pressed = gamepads.iter().any(|gamepad| {
+        gamepad_input.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::Start))
+    });
+    if (keyboard_input.any_just_pressed([KeyCode::Space, KeyCode::Escape]) || start_pressed)
+        && matches!(
+            app_state.current(),
+            AppState::InGame(PlayState::Running | PlayState::Rewinding)
+        )
+    {
+        app_state.transition(AppState::InGame(PlayState::Paused));
     }
 }
 
-pub fn clear_snake(mut commands: Commands, query: Query<Entity, With<Point>>) {
+pub fn clear_snake(mut commands: Commands, query: Query<Entity, With<Snake>>) {
     for entity in &query {
-        commands.entity(entity).despawn();
+        commands.entity(entity).despawn_recursive();
     }
 }
 
-pub fn clear_food(
-    mut commands: Commands,
-    query: Query<Entity, With<Food>>,
-    mut timer_query: Query<&mut FoodTimer>,
-) {
+pub fn clear_food(mut commands: Commands, query: Query<Entity, Or<(With<Food>, With<FoodTimer>)>>) {
     for entity in &query {
         commands.entity(entity).despawn();
     }
-    for mut timer in &mut timer_query {
-        timer.0.pause(); // TODO 移除timer
-    }
 }
@@ -0,0 +1,61 @@
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+// 存档目录：SNAKE_DATA_DIR 优先，其次是系统的用户数据目录
+fn data_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("SNAKE_DATA_DIR") {
+        return PathBuf::from(dir);
+    }
+    if let Some(dir) = std::env::var_os("XDG_DATA_HOME") {
+        return PathBuf::from(dir).join("snake");
+    }
+    if let Some(dir) = std::env::var_os("APPDATA") {
+        return PathBuf::from(dir).join("snake");
+    }
+    if let Some(dir) = std::env::var_os("HOME") {
+        return PathBuf::from(dir).join(".local/share/snake");
+    }
+    PathBuf::from(".")
+}
+
+// 读取存档，文件不存在或格式错误时返回默认值
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> T {
+    try_load(name).unwrap_or_default()
+}
+
+pub fn try_load<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let path = data_dir().join(name);
+    let text = fs::read_to_string(&path).ok()?;
+    ron::from_str(&text)
+        .map_err(|err| warn!("failed to parse {}: {}", path.display(), err))
+        .ok()
+}
+
+pub fn save<T: Serialize>(name: &str, value: &T) {
+    let dir = data_dir();
+    let text = match ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()) {
+        Ok(text) => text,
+        Err(err) => {
+            warn!("failed to serialize {}: {}", name, err);
+            return;
+        }
+    };
+    if let Err(err) = fs::create_dir_all(&dir).and_then(|_| fs::write(dir.join(name), text)) {
+        warn!("failed to save {}: {}", dir.join(name).display(), err);
+    }
+}
+
+pub fn exists(name: &str) -> bool {
+    data_dir().join(name).is_file()
+}
+
+pub fn remove(name: &str) {
+    let path = data_dir().join(name);
+    if let Err(err) = fs::remove_file(&path) {
+        if err.kind() != std::io::ErrorKind::NotFound {
+            warn!("failed to remove {}: {}", path.display(), err);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use bevy::{prelude::*, reflect::TypeUuid};
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    board::{BoardBorder, GridCell},
+    hazard::Hazard,
+    level::{portal_color, Portal},
+    settings::Settings,
+    snake::FoodAssets,
+};
+
+// 内置主题：文件名和显示名称的 key
+pub const BUILTIN_THEMES: [(&str, &str); 3] = [
+    ("dark", "theme.dark"),
+    ("classic", "theme.classic"),
+    ("high_contrast", "theme.high_contrast"),
+];
+
+// 主题配色，从 assets/themes/*.theme.ron 加载，颜色写成十六进制
+#[derive(Resource, Deserialize, TypeUuid, Clone)]
+#[uuid = "6a4f3c9e-2d1b-4e8a-9f57-0c3b8d2e71a4"]
+pub struct Theme {
+    #[serde(deserialize_with = "hex_color")]
+    pub background: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub snake_head: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub snake_tail: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub food: Color,
+    // 金色食物
+    #[serde(deserialize_with = "hex_color")]
+    pub bonus_food: Color,
+    // 限时模式中加时间的食物
+    #[serde(deserialize_with = "hex_color")]
+    pub clock_food: Color,
+    // 第一对传送门的颜色，其他的改变色相
+    #[serde(deserialize_with = "hex_color")]
+    pub portal: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub hazard: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub border: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub grid: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub menu_background: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub title_text: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub button: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub button_hovered: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub button_pressed: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub button_text: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub hud_label: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub hud_value: Color,
+}
+
+// 主题文件加载完成之前使用的配色，和 dark 主题一致
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            background: Color::rgb(49.0 / 255.0, 44.0 / 255.0, 63.0 / 255.0),
+            snake_head: Color::rgb(0.5, 1.0, 0.5),
+            snake_tail: Color::rgb(0.15, 0.55, 0.3),
+            food: Color::RED,
+            bonus_food: Color::GOLD,
+            clock_food: Color::CYAN,
+            portal: Color::rgb(0.3, 0.5, 1.0),
+            hazard: Color::ORANGE_RED,
+            border: Color::rgb(0.55, 0.5, 0.7),
+            grid: Color::rgba(1.0, 1.0, 1.0, 0.04),
+            menu_background: Color::CRIMSON,
+            title_text: Color::rgb(0.9, 0.9, 0.9),
+            button: Color::rgb(0.15, 0.15, 0.15),
+            button_hovered: Color::rgb(0.25, 0.25, 0.25),
+            button_pressed: Color::rgb(0.35, 0.75, 0.35),
+            button_text: Color::rgb(0.9, 0.9, 0.9),
+            hud_label: Color::rgba(0.5, 0.5, 1.0, 0.5),
+            hud_value: Color::rgba(1.0, 0.5, 0.5, 0.5),
+        }
+    }
+}
+
+fn hex_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let hex = String::deserialize(deserializer)?;
+    Color::hex(hex.trim_start_matches('#'))
+        .map_err(|err| serde::de::Error::custom(format!("invalid color {hex}: {err:?}")))
+}
+
+#[derive(Resource)]
+pub struct ThemeHandles(Vec<Handle<Theme>>);
+
+// 需要跟随主题变色的文字
+#[derive(Component)]
+pub enum ThemedText {
+    Title,
+    Button,
+    Hud,
+}
+
+// 菜单面板背景
+#[derive(Component)]
+pub struct MenuPanel;
+
+pub fn setup_themes(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handles = BUILTIN_THEMES
+        .iter()
+        .map(|(file, _)| asset_server.load(format!("themes/{file}.theme.ron")))
+        .collect();
+    commands.insert_resource(ThemeHandles(handles));
+}
+
+// 切换主题或者主题文件被修改后，更新当前主题
+pub fn select_theme(
+    settings: Res<Settings>,
+    handles: Res<ThemeHandles>,
+    themes: Res<Assets<Theme>>,
+    mut events: EventReader<AssetEvent<Theme>>,
+    mut theme: ResMut<Theme>,
+) {
+    let Some(handle) = handles.0.get(settings.theme) else {
+        return;
+    };
+    let reloaded = events.iter().any(|event| match event {
+        AssetEvent::Created { handle: changed } | AssetEvent::Modified { handle: changed } => {
+            changed == handle
+        }
+        AssetEvent::Removed { .. } => false,
+    });
+    if settings.is_changed() || reloaded {
+        if let Some(selected) = themes.get(handle) {
+            *theme = selected.clone();
+        }
+    }
+}
+
+pub fn apply_theme(
+    theme: Res<Theme>,
+    mut clear_color: ResMut<ClearColor>,
+    mut text_query: Query<(&mut Text, &ThemedText)>,
+    mut panel_query: Query<&mut BackgroundColor, With<MenuPanel>>,
+    food_assets: Res<FoodAssets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut border_query: Query<&mut Sprite, (With<BoardBorder>, Without<GridCell>)>,
+    mut grid_query: Query<&mut Sprite, With<GridCell>>,
+    mut portal_query: Query<(&mut Sprite, &Portal), (Without<BoardBorder>, Without<GridCell>)>,
+    mut hazard_query: Query<
+        &mut Sprite,
+        (
+            With<Hazard>,
+            Without<BoardBorder>,
+            Without<GridCell>,
+            Without<Portal>,
+        ),
+    >,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+    clear_color.0 = theme.background;
+    for (mut text, themed) in &mut text_query {
+        for (i, section) in text.sections.iter_mut().enumerate() {
+            section.style.color = match themed {
+                ThemedText::Title => theme.title_text,
+                ThemedText::Button => theme.button_text,
+                ThemedText::Hud if i == 0 => theme.hud_label,
+                ThemedText::Hud => theme.hud_value,
+            };
+        }
+    }
+    for mut background in &mut panel_query {
+        *background = theme.menu_background.into();
+    }
+    food_assets.apply_theme(&mut materials, &theme);
+    for mut sprite in &mut border_query {
+        sprite.color = theme.border;
+    }
+    for mut sprite in &mut grid_query {
+        sprite.color = theme.grid;
+    }
+    for (mut sprite, portal) in &mut portal_query {
+        sprite.color = portal_color(&theme, portal.0);
+    }
+    for mut sprite in &mut hazard_query {
+        sprite.color = theme.hazard;
+    }
+}
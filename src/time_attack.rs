@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::GameClock,
+    locale::{Localized, UiFonts},
+    mode::{CurrentRun, GameMode},
+    score::{hud_item_style, HudRow},
+    snake::{DeathCause, FoodEatenEvent, FoodKind, GameEnd, Point, Snake},
+    theme::{Theme, ThemedText},
+};
+
+// 主菜单中可选的限时秒数
+pub const TIME_ATTACK_LIMITS: [u32; 2] = [60, 120];
+
+// 吃到时钟食物增加的秒数
+pub const CLOCK_BONUS: f32 = 5.0;
+
+// 限时模式的剩余秒数
+#[derive(Resource, Default, Clone, Serialize, Deserialize)]
+pub struct Countdown(f32);
+
+impl Countdown {
+    // 距离时间用完还有多久，已经用完时返回 None
+    pub fn remaining(&self) -> Option<Duration> {
+        (self.0 > 0.0).then(|| Duration::from_secs_f32(self.0))
+    }
+}
+
+#[derive(Component)]
+pub struct Timeboard;
+
+pub fn setup_timeboard(
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    theme: Res<Theme>,
+    row: Res<HudRow>,
+) {
+    let board = commands
+        .spawn(
+            TextBundle::from_sections([
+                TextSection::new(
+                    "Time: ",
+                    TextStyle {
+                        font: fonts.bold.clone(),
+                        font_size: 30.0,
+                        color: theme.hud_label,
+                    },
+                ),
+                TextSection::new(
+                    "0:00",
+                    TextStyle {
+                        font: fonts.mono.clone(),
+                        font_size: 30.0,
+                        color: theme.hud_value,
+                    },
+                ),
+            ])
+            .with_style(hud_item_style()),
+        )
+        .insert((
+            Timeboard,
+            ThemedText::Hud,
+            Localized("hud.time".to_string()),
+        ))
+        .id();
+    commands.entity(row.0).add_child(board);
+}
+
+pub fn reset_countdown(run: Res<CurrentRun>, mut countdown: ResMut<Countdown>) {
+    countdown.0 = match run.mode {
+        GameMode::TimeAttack(seconds) => seconds as f32,
+        _ => 0.0,
+    };
+}
+
+// 时间用完时和撞死一样进入死亡动画和游戏结束界面
+pub fn tick_countdown(
+    clock: Res<GameClock>,
+    run: Res<CurrentRun>,
+    mut countdown: ResMut<Countdown>,
+    mut events: EventReader<FoodEatenEvent>,
+    snake_query: Query<&Children, With<Snake>>,
+    point_query: Query<&Point>,
+    mut game_end: GameEnd,
+) {
+    if !matches!(run.mode, GameMode::TimeAttack(_)) {
+        return;
+    }
+    for event in events.iter() {
+        if event.kind == FoodKind::Clock {
+            countdown.0 += CLOCK_BONUS;
+        }
+    }
+    // 时间已经用完过，只结束一次（无敌时继续玩，吃到时钟食物后重新计时）
+    if countdown.0 <= 0.0 {
+        return;
+    }
+    countdown.0 -= clock.delta_seconds();
+    if countdown.0 > 0.0 {
+        return;
+    }
+    countdown.0 = 0.0;
+
+    let head = snake_query
+        .iter()
+        .find_map(|children| point_query.get(children[0]).ok().copied())
+        .unwrap_or(Point::new(0, 0));
+    game_end.die(head, DeathCause::TimeUp);
+}
+
+pub fn update_timeboard(
+    run: Res<CurrentRun>,
+    countdown: Res<Countdown>,
+    mut query: Query<(&mut Text, &mut Style), With<Timeboard>>,
+) {
+    if !run.is_changed() && !countdown.is_changed() {
+        return;
+    }
+    let (mut text, mut style) = query.single_mut();
+    let display = if matches!(run.mode, GameMode::TimeAttack(_)) {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    // 只在变化时修改，避免每帧重新布局
+    if style.display != display {
+        style.display = display;
+    }
+    let seconds = countdown.0.ceil() as u32;
+    text.sections[1].value = format!("{}:{:02}", seconds / 60, seconds % 60);
+}
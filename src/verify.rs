@@ -0,0 +1,383 @@
+use std::{fmt, fs, ops::RangeInclusive};
+
+use crate::{
+    campaign::{Campaign, CampaignGoal},
+    hazard::{next_hazard_point, HazardState},
+    leaderboard::Submission,
+    level::{CustomLevel, Level},
+    mode::{seed_from_str, CurrentRun, GameMode},
+    replay::Replay,
+    score::RunStats,
+    shrink::{next_ring, MAX_MARGIN, SHRINK_INTERVAL},
+    snake::{place_food, Direction, Food, FoodKind, Point, FOOD_DELAY, MOVE_INTERVAL},
+    time_attack::CLOCK_BONUS,
+};
+
+// 游戏中累计的是浮点时间，结束的时刻和模拟只有很小的误差
+const TIME_TOLERANCE: u64 = 50;
+
+// 模拟中的时间以毫秒计，避免浮点误差
+fn millis(seconds: f32) -> u64 {
+    (seconds * 1000.0).round() as u64
+}
+
+// 第 step 步（从 0 开始）移动的时刻
+fn step_time(step: usize) -> u64 {
+    (step as u64 + 1) * millis(MOVE_INTERVAL)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    // 不计入排行榜的模式
+    Unranked(GameMode),
+    UnknownLevel(usize),
+    // 种子和闯关的关卡或者每日挑战的日期不符
+    WrongSeed,
+    // 第几步撞到墙或者自己
+    Crashed(usize),
+    // 一局已经结束，第几步不可能发生
+    AfterEnd(usize),
+    // 记录中吃到食物的步数和模拟不一致
+    EatenMismatch,
+    ScoreMismatch { claimed: u32, min: u32, max: u32 },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::Unranked(mode) => write!(f, "{} is not ranked", mode.table()),
+            VerifyError::UnknownLevel(index) => write!(f, "unknown campaign level {index}"),
+            VerifyError::WrongSeed => write!(f, "seed does not match the level or date"),
+            VerifyError::Crashed(step) => write!(f, "step {step} crashes"),
+            VerifyError::AfterEnd(step) => write!(f, "step {step} happens after the run ended"),
+            VerifyError::EatenMismatch => write!(f, "recorded food does not match"),
+            VerifyError::ScoreMismatch { claimed, min, max } if min == max => {
+                write!(f, "claimed score {claimed}, expected {min}")
+            }
+            VerifyError::ScoreMismatch { claimed, min, max } => {
+                write!(f, "claimed score {claimed}, expected {min} to {max}")
+            }
+        }
+    }
+}
+
+// 不显示画面、按理想时间重新模拟一局
+// 游戏中的 GameClock 每帧最多推进到下一个定时事件，不同时刻的事件不会在同一帧中处理
+// 同一时刻的事件按游戏中系统的顺序处理：先移动蛇，再倒计时、缩圈、移动障碍、
+// 检查碰撞和闯关目标，最后生成食物
+pub struct Simulation {
+    run: CurrentRun,
+    level: Level,
+    // 从蛇头到蛇尾
+    body: Vec<Point>,
+    food: Vec<Food>,
+    // 等待生成食物的时刻
+    food_timers: Vec<u64>,
+    // 障碍和下一次移动的时刻
+    hazards: Vec<(HazardState, u64)>,
+    next_shrink: u64,
+    // 限时模式结束的时刻
+    deadline: Option<u64>,
+    goal: Option<CampaignGoal>,
+    stats: RunStats,
+    score: u32,
+    steps: usize,
+    eaten: Vec<usize>,
+    // 撞到障碍、被墙压到、时间用完或者完成闯关目标的时刻
+    ended: Option<u64>,
+}
+
+impl Simulation {
+    pub fn new(mode: GameMode, seed: u64, campaign: &Campaign) -> Result<Self, VerifyError> {
+        let goal = match mode {
+            GameMode::Custom | GameMode::Practice => return Err(VerifyError::Unranked(mode)),
+            GameMode::Campaign(index) => {
+                let level = campaign
+                    .level(index)
+                    .ok_or(VerifyError::UnknownLevel(index))?;
+                // 闯关每一关的种子是固定的
+                if seed != seed_from_str(&mode.table()) {
+                    return Err(VerifyError::WrongSeed);
+                }
+                Some(level.goal)
+            }
+            _ => None,
+        };
+        let run = CurrentRun::new(mode, seed);
+        let level = Level::for_run(&run, campaign, &CustomLevel::default());
+        let hazards = level
+            .initial_hazards()
+            .into_iter()
+            .map(|state| {
+                let next = millis(state.interval);
+                (state, next)
+            })
+            .collect();
+        Ok(Self {
+            body: level.snake_body(),
+            food: Vec::new(),
+            food_timers: vec![millis(FOOD_DELAY)],
+            hazards,
+            next_shrink: millis(SHRINK_INTERVAL),
+            deadline: match mode {
+                GameMode::TimeAttack(seconds) => Some(seconds as u64 * 1000),
+                _ => None,
+            },
+            goal,
+            stats: RunStats::default(),
+            score: 0,
+            steps: 0,
+            eaten: Vec::new(),
+            ended: None,
+            run,
+            level,
+        })
+    }
+
+    pub fn level(&self) -> &Level {
+        &self.level
+    }
+
+    pub fn body(&self) -> &[Point] {
+        &self.body
+    }
+
+    pub fn food(&self) -> &[Food] {
+        &self.food
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    // 按记录的方向走一步，返回是否吃到食物
+    pub fn step(&mut self, direction: Direction) -> Result<bool, VerifyError> {
+        let step = self.steps;
+        let time = step_time(step);
+        self.advance(time);
+        if self.ended.is_some() {
+            return Err(VerifyError::AfterEnd(step));
+        }
+
+        let head = self.body[0];
+        let next = self.level.step(&head, &direction);
+        if self.level.is_blocked(&next) || self.body.contains(&next) {
+            return Err(VerifyError::Crashed(step));
+        }
+        let mut eaten = None;
+        self.food.retain(|food| {
+            if food.point == next {
+                eaten = Some(food.kind);
+            }
+            food.point != next
+        });
+        self.body.insert(0, next);
+        match eaten {
+            Some(kind) => {
+                self.score += kind.score();
+                *self.stats.food_eaten.entry(kind).or_default() += 1;
+                self.eaten.push(step);
+                self.food_timers.push(time + millis(FOOD_DELAY));
+                if let (FoodKind::Clock, Some(deadline)) = (kind, &mut self.deadline) {
+                    *deadline += millis(CLOCK_BONUS);
+                }
+            }
+            None => {
+                self.body.pop();
+            }
+        }
+        self.steps += 1;
+        self.run_events(time, Some(head), eaten.is_some());
+        Ok(eaten.is_some())
+    }
+
+    // 最后一步之后的下一次移动撞死，缩圈模式的存活秒数按结束的时刻计算
+    pub fn final_score(&mut self) -> RangeInclusive<u32> {
+        if !self.level.shrinking {
+            return self.score..=self.score;
+        }
+        let next_move = step_time(self.steps);
+        self.advance(next_move);
+        let end = self.ended.unwrap_or(next_move);
+        let min = end.saturating_sub(TIME_TOLERANCE) / 1000;
+        let max = (end + TIME_TOLERANCE) / 1000;
+        self.score + min as u32..=self.score + max as u32
+    }
+
+    // 处理 until 之前的定时事件
+    fn advance(&mut self, until: u64) {
+        while self.ended.is_none() {
+            match self.next_event() {
+                Some(time) if time < until => self.run_events(time, None, false),
+                _ => break,
+            }
+        }
+    }
+
+    fn next_event(&self) -> Option<u64> {
+        let shrink =
+            (self.level.shrinking && self.level.margin < MAX_MARGIN).then_some(self.next_shrink);
+        let survive = match self.goal {
+            Some(CampaignGoal::Survive(seconds)) => Some(seconds as u64 * 1000),
+            _ => None,
+        };
+        self.food_timers
+            .iter()
+            .copied()
+            .chain(self.hazards.iter().map(|(_, next)| *next))
+            .chain(shrink)
+            .chain(self.deadline)
+            .chain(survive)
+            .min()
+    }
+
+    // 处理 time 时刻的事件，moved_from 为同一时刻移动前的蛇头位置
+    // 同一帧中新长出的蛇头还没有生成，grew 时生成食物不考虑蛇头
+    fn run_events(&mut self, time: u64, moved_from: Option<Point>, grew: bool) {
+        self.stats.time = time as f32 / 1000.0;
+        if self.deadline.is_some_and(|deadline| deadline <= time) {
+            self.ended = Some(time);
+            return;
+        }
+
+        if self.level.shrinking && self.level.margin < MAX_MARGIN && self.next_shrink == time {
+            self.next_shrink += millis(SHRINK_INTERVAL);
+            let ring = next_ring(&self.level);
+            self.level.margin += 1;
+            // 墙里的食物换个位置重新生成
+            let buried = self
+                .food
+                .iter()
+                .filter(|food| ring.contains(&food.point))
+                .count();
+            self.food_timers
+                .extend(std::iter::repeat_n(time + millis(FOOD_DELAY), buried));
+            self.food.retain(|food| !ring.contains(&food.point));
+            if self.body.iter().any(|point| ring.contains(point)) {
+                self.ended = Some(time);
+                return;
+            }
+        }
+
+        // 同一帧中障碍看到的蛇头还是移动前的位置
+        let head = moved_from.unwrap_or(self.body[0]);
+        // 障碍和蛇头互换了位置
+        let mut swapped = false;
+        for (hazard, next) in &mut self.hazards {
+            if *next != time {
+                continue;
+            }
+            *next += millis(hazard.interval);
+            let from = hazard.point;
+            hazard.point = next_hazard_point(
+                &self.level,
+                hazard.point,
+                &mut hazard.motion,
+                &mut hazard.waypoint,
+                Some(head),
+            );
+            swapped |= moved_from == Some(hazard.point) && from == self.body[0];
+        }
+        if swapped
+            || self
+                .hazards
+                .iter()
+                .any(|(hazard, _)| self.body.contains(&hazard.point))
+        {
+            self.ended = Some(time);
+            return;
+        }
+
+        if let Some(goal) = self.goal {
+            if goal.progress(&self.stats, self.body.len()) >= goal.target() {
+                self.ended = Some(time);
+                return;
+            }
+        }
+
+        let due = self
+            .food_timers
+            .iter()
+            .filter(|timer| **timer == time)
+            .count();
+        self.food_timers.retain(|timer| *timer != time);
+        for _ in 0..due {
+            let time_bonus = matches!(self.run.mode, GameMode::TimeAttack(_));
+            let mut rng = self.run.food_rng();
+            let body = &self.body[usize::from(grew)..];
+            let hazards = &self.hazards;
+            let is_taken = |point: &Point| {
+                body.contains(point) || hazards.iter().any(|(hazard, _)| hazard.point == *point)
+            };
+            match place_food(&self.level, &mut rng, time_bonus, is_taken) {
+                Some(food) => self.food.push(food),
+                // 没有空位时稍后再试
+                None => self.food_timers.push(time + millis(FOOD_DELAY)),
+            }
+        }
+    }
+}
+
+// 重新模拟一局，操作合法、吃到食物的步数和分数都一致时才接受
+pub fn verify(replay: &Replay, campaign: &Campaign) -> Result<(), VerifyError> {
+    // 每日挑战的种子由日期决定，不能换成别的种子
+    if replay.mode == GameMode::Daily
+        && replay.date.as_deref().map(seed_from_str) != Some(replay.seed)
+    {
+        return Err(VerifyError::WrongSeed);
+    }
+    let mut simulation = Simulation::new(replay.mode, replay.seed, campaign)?;
+    for direction in &replay.steps {
+        simulation.step(*direction)?;
+    }
+    if simulation.eaten != replay.eaten {
+        return Err(VerifyError::EatenMismatch);
+    }
+    let expected = simulation.final_score();
+    if !expected.contains(&replay.score) {
+        return Err(VerifyError::ScoreMismatch {
+            claimed: replay.score,
+            min: *expected.start(),
+            max: *expected.end(),
+        });
+    }
+    Ok(())
+}
+
+// 读取操作记录文件：单独的一局（RON 或 JSON）或者提交到服务器的内容（JSON）
+fn load_replay(path: &str) -> Result<Replay, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("failed to read {path}: {err}"))?;
+    if let Ok(submission) = serde_json::from_str::<Submission>(&text) {
+        return Ok(submission.replay);
+    }
+    if let Ok(replay) = serde_json::from_str::<Replay>(&text) {
+        return Ok(replay);
+    }
+    ron::from_str(&text).map_err(|err| format!("failed to parse {path}: {err}"))
+}
+
+// snake verify <replay>，返回进程的退出码
+pub fn verify_command(path: &str) -> i32 {
+    let replay = match load_replay(path) {
+        Ok(replay) => replay,
+        Err(err) => {
+            eprintln!("{err}");
+            return 2;
+        }
+    };
+    match verify(&replay, &Campaign::default()) {
+        Ok(()) => {
+            println!(
+                "valid: {} score {} in {} steps",
+                replay.mode.table(),
+                replay.score,
+                replay.steps.len()
+            );
+            0
+        }
+        Err(err) => {
+            println!("invalid: {err}");
+            1
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// 每个测试文件只用到其中一部分
+#![allow(dead_code)]
+
+use std::collections::{HashSet, VecDeque};
+
+use snake::{
+    campaign::Campaign,
+    level::Level,
+    mode::{seed_from_str, GameMode},
+    replay::Replay,
+    snake::{Direction, Point},
+    verify::{Simulation, VerifyError},
+};
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+// 简单的自动玩家：沿最短路径去吃最近的食物，没有路时随便走一步安全的格子
+// 分数达到 target、无路可走或者一局结束时停下，返回这一局的操作记录
+pub fn play(mode: GameMode, seed: u64, target: u32) -> Replay {
+    let mut simulation = Simulation::new(mode, seed, &Campaign::default()).unwrap();
+    let mut steps = Vec::new();
+    let mut eaten = Vec::new();
+    while simulation.score() < target && steps.len() < 5000 {
+        let food: Vec<Point> = simulation.food().iter().map(|food| food.point).collect();
+        let Some(direction) = next_direction(simulation.level(), simulation.body(), &food) else {
+            break;
+        };
+        match simulation.step(direction) {
+            Ok(ate) => {
+                if ate {
+                    eaten.push(steps.len());
+                }
+                steps.push(direction);
+            }
+            Err(VerifyError::AfterEnd(_)) => break,
+            Err(err) => panic!("bot crashed: {err}"),
+        }
+    }
+    Replay {
+        mode,
+        seed,
+        date: None,
+        score: *simulation.final_score().start(),
+        steps,
+        eaten,
+    }
+}
+
+// 某一天的每日挑战
+pub fn play_daily(date: &str, target: u32) -> Replay {
+    Replay {
+        date: Some(date.to_string()),
+        ..play(GameMode::Daily, seed_from_str(date), target)
+    }
+}
+
+fn is_safe(level: &Level, body: &[Point], point: &Point) -> bool {
+    !level.is_blocked(point) && !body.contains(point)
+}
+
+// 广度优先搜索到最近食物的第一步，body 从蛇头到蛇尾
+pub fn next_direction(level: &Level, body: &[Point], food: &[Point]) -> Option<Direction> {
+    let head = body[0];
+    let food: HashSet<Point> = food.iter().copied().collect();
+    let mut visited = HashSet::from([head]);
+    let mut queue = VecDeque::new();
+    for direction in DIRECTIONS {
+        let point = level.step(&head, &direction);
+        if is_safe(level, body, &point) && visited.insert(point) {
+            queue.push_back((point, direction));
+        }
+    }
+    let fallback = queue.front().map(|(_, direction)| *direction);
+    while let Some((point, first)) = queue.pop_front() {
+        if food.contains(&point) {
+            return Some(first);
+        }
+        for direction in DIRECTIONS {
+            let next = level.step(&point, &direction);
+            if is_safe(level, body, &next) && visited.insert(next) {
+                queue.push_back((next, first));
+            }
+        }
+    }
+    fallback
+}
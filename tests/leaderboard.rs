@@ -0,0 +1,142 @@
+mod common;
+
+use std::{net::TcpListener, path::PathBuf, thread};
+
+use common::{play, play_daily};
+use snake::{
+    leaderboard::{fetch_scores, submit, RequestError, ScoreEntry, Submission},
+    mode::{daily_table, today, GameMode},
+    replay::Replay,
+    score_server::ScoreServer,
+};
+
+// 在随机端口启动参考服务器，返回地址
+fn start_server(path: &PathBuf) -> String {
+    let _ = std::fs::remove_file(path);
+    let server = ScoreServer::open(path).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || server.run(listener));
+    format!("http://{addr}")
+}
+
+fn store(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("snake-{}-{}.json", name, std::process::id()))
+}
+
+fn submission(player: &str, replay: &Replay) -> Submission {
+    Submission {
+        player: player.to_string(),
+        replay: replay.clone(),
+    }
+}
+
+fn entry(player: &str, score: u32) -> ScoreEntry {
+    ScoreEntry {
+        player: player.to_string(),
+        score,
+    }
+}
+
+#[test]
+fn submitted_scores_are_ranked_per_table() {
+    let path = store("ranked");
+    let server = start_server(&path);
+    let low = play(GameMode::Classic, 1, 3);
+    let middle = play(GameMode::Classic, 2, low.score + 3);
+    let high = play(GameMode::Classic, 3, middle.score + 3);
+    let timed = play(GameMode::TimeAttack(60), 4, 5);
+    submit(&server, &submission("alice", &low)).unwrap();
+    submit(&server, &submission("bob", &middle)).unwrap();
+    submit(&server, &submission("alice", &high)).unwrap();
+    submit(&server, &submission("carol", &timed)).unwrap();
+
+    // 每个玩家只保留最好成绩
+    let classic = fetch_scores(&server, "classic", 50).unwrap();
+    assert_eq!(
+        classic,
+        vec![entry("alice", high.score), entry("bob", middle.score)]
+    );
+    assert_eq!(
+        fetch_scores(&server, "classic", 1).unwrap(),
+        vec![entry("alice", high.score)]
+    );
+    assert_eq!(
+        fetch_scores(&server, "time_attack_60", 50).unwrap(),
+        vec![entry("carol", timed.score)]
+    );
+    assert!(fetch_scores(&server, "shrinking", 50).unwrap().is_empty());
+
+    // 重启后仍然保留
+    let reopened = ScoreServer::open(&path).unwrap();
+    assert_eq!(reopened.top("classic", 50), classic);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn daily_scores_count_the_first_submission_per_day() {
+    let path = store("daily");
+    let server = start_server(&path);
+    let date = today();
+    let first = play_daily(&date, 3);
+    let second = play_daily(&date, first.score + 3);
+    submit(&server, &submission("alice", &first)).unwrap();
+    let result = submit(&server, &submission("alice", &second));
+    assert!(matches!(result, Err(RequestError::Rejected(400, _))));
+    submit(&server, &submission("bob", &second)).unwrap();
+
+    assert_eq!(
+        fetch_scores(&server, &daily_table(&date), 50).unwrap(),
+        vec![entry("bob", second.score), entry("alice", first.score)]
+    );
+    assert!(fetch_scores(&server, "daily", 50).unwrap().is_empty());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn invalid_submissions_are_rejected() {
+    let path = store("rejected");
+    let server = start_server(&path);
+    let replay = play(GameMode::Classic, 1, 5);
+    let result = submit(&server, &submission("  ", &replay));
+    assert!(matches!(result, Err(RequestError::Rejected(400, _))));
+    let result = submit(
+        &server,
+        &submission("alice", &play(GameMode::Classic, 1, 0)),
+    );
+    assert!(matches!(result, Err(RequestError::Rejected(400, _))));
+    // 分数和重新模拟的结果不符
+    let mut tampered = replay;
+    tampered.score += 10;
+    let result = submit(&server, &submission("alice", &tampered));
+    assert!(matches!(result, Err(RequestError::Rejected(400, _))));
+    assert!(fetch_scores(&server, "classic", 50).unwrap().is_empty());
+    // 过去和将来的每日挑战
+    for date in ["2023-02-01", "9999-12-31"] {
+        let result = submit(&server, &submission("alice", &play_daily(date, 3)));
+        assert!(
+            matches!(result, Err(RequestError::Rejected(400, _))),
+            "{date}"
+        );
+    }
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn unreachable_server_is_offline() {
+    // 拿到一个空闲端口后关闭，连接会被拒绝
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let server = format!("http://{addr}");
+    let result = submit(
+        &server,
+        &submission("alice", &play(GameMode::Classic, 1, 3)),
+    );
+    assert!(matches!(result, Err(RequestError::Offline(_))));
+    assert!(matches!(
+        fetch_scores(&server, "classic", 50),
+        Err(RequestError::Offline(_))
+    ));
+}
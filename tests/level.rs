@@ -0,0 +1,43 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use snake::{
+    hazard::HazardSpec,
+    level::Level,
+    snake::{Point, X, Y},
+};
+
+// 除了蛇身之外全是墙的关卡
+fn walled_in() -> Level {
+    let mut level = Level::default();
+    let body = level.snake_body();
+    level.walls = (-X..=X)
+        .flat_map(|x| (-Y..=Y).map(move |y| Point::new(x, y)))
+        .filter(|point| !body.contains(point))
+        .collect();
+    level
+}
+
+#[test]
+fn portals_are_skipped_when_there_is_no_room() {
+    let level = walled_in().with_random_portals(&mut ChaCha8Rng::seed_from_u64(1));
+    assert!(level.portals.is_empty());
+}
+
+#[test]
+fn hazards_are_skipped_when_there_is_no_room() {
+    let level = walled_in().with_random_hazards(&mut ChaCha8Rng::seed_from_u64(1));
+    assert!(level.hazards.is_empty());
+}
+
+#[test]
+fn hazard_intervals_must_be_positive() {
+    let spec = |interval: &str| {
+        ron::from_str::<HazardSpec>(&format!(
+            "(start: (x: 0, y: 0), motion: Chase, interval: {interval})"
+        ))
+    };
+    assert_eq!(spec("0.3").unwrap().interval, 0.3);
+    for interval in ["0.0", "-1.0", "inf", "NaN"] {
+        assert!(spec(interval).is_err(), "{interval}");
+    }
+}
@@ -0,0 +1,811 @@
+mod common;
+
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    input::{keyboard::KeyboardInput, ButtonState, InputPlugin},
+    prelude::*,
+    time::TimeUpdateStrategy,
+    window::WindowPlugin,
+};
+use common::next_direction;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use snake::{
+    achievements::AchievementToast,
+    campaign::Campaign,
+    common::{AppState, PlayState, Transition},
+    editor::{EditorAction, OnEditorScreen},
+    game::GamePlugin,
+    hazard::{HazardMotion, HazardSpec},
+    level::Level,
+    menu::{
+        MenuButtonAction, OnGameOverMenuScreen, OnGamePausedMenuScreen, OnMainMenuScreen,
+        OnSettingsMenuScreen,
+    },
+    mode::{CurrentRun, GameMode},
+    replay::RunRecorder,
+    score::{HighScores, Record, RunStats, Score},
+    settings::{Setting, Settings},
+    snake::{Direction, Food, FoodKind, FoodTimer, Point, Snake, MOVE_INTERVAL},
+    verify::verify,
+};
+
+// 每帧固定 50 毫秒，蛇每 3 帧走一步
+const FRAME: Duration = Duration::from_millis(50);
+
+// 存档目录通过环境变量指定，同一时间只运行一局
+static LOCK: Mutex<()> = Mutex::new(());
+static GAMES: AtomicUsize = AtomicUsize::new(0);
+
+// 不打开窗口、不渲染的游戏，时间由测试推进
+struct Game {
+    app: App,
+    now: Instant,
+    dir: PathBuf,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Game {
+    fn new() -> Self {
+        Self::with_plugins(|_| {})
+    }
+
+    // 第一帧之后再添加系统会打乱调度，额外的插件在这里添加
+    fn with_plugins(add_plugins: impl FnOnce(&mut App)) -> Self {
+        // 别的测试失败时锁会中毒，不影响这里
+        let lock = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        // 每一局的存档写到单独的临时目录
+        let dir = std::env::temp_dir().join(format!(
+            "snake-states-{}-{}",
+            std::process::id(),
+            GAMES.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::env::set_var("SNAKE_DATA_DIR", &dir);
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_plugin(WindowPlugin {
+                add_primary_window: false,
+                exit_on_all_closed: false,
+                ..default()
+            })
+            .add_plugin(InputPlugin)
+            .add_plugin(TransformPlugin)
+            .add_plugin(HierarchyPlugin)
+            .add_asset::<Image>()
+            .add_asset::<Font>()
+            .add_asset::<Mesh>()
+            .add_asset::<ColorMaterial>()
+            .add_asset::<TextureAtlas>()
+            .add_asset::<AudioSource>()
+            .init_resource::<Audio>()
+            .init_resource::<ClearColor>()
+            .add_plugin(GamePlugin);
+        add_plugins(&mut app);
+        let mut game = Self {
+            app,
+            now: Instant::now(),
+            dir,
+            _lock: lock,
+        };
+        game.update();
+        game
+    }
+
+    fn update(&mut self) {
+        self.update_by(FRAME);
+    }
+
+    fn update_by(&mut self, delta: Duration) {
+        self.now += delta;
+        self.app
+            .insert_resource(TimeUpdateStrategy::ManualInstant(self.now));
+        self.app.update();
+    }
+
+    fn run(&mut self, seconds: f32) {
+        for _ in 0..(seconds / FRAME.as_secs_f32()).round() as usize {
+            self.update();
+        }
+    }
+
+    // 推进到 app_state 为 state，最多 seconds 秒
+    fn run_until(&mut self, state: AppState, seconds: f32) {
+        for _ in 0..(seconds / FRAME.as_secs_f32()).round() as usize {
+            if self.app_state() == state {
+                return;
+            }
+            self.update();
+        }
+        assert_eq!(self.app_state(), state);
+    }
+
+    fn app_state(&self) -> AppState {
+        *self.app.world.resource::<State<AppState>>().current()
+    }
+
+    fn count<C: Component>(&mut self) -> usize {
+        self.app
+            .world
+            .query_filtered::<(), With<C>>()
+            .iter(&self.app.world)
+            .count()
+    }
+
+    // 点击菜单中第一个符合条件的按钮，再推进两帧让状态切换完成
+    fn click(&mut self, is_target: impl Fn(&MenuButtonAction) -> bool) {
+        let world = &mut self.app.world;
+        let button = world
+            .query_filtered::<(Entity, &MenuButtonAction), With<Button>>()
+            .iter(world)
+            .find(|(_, action)| is_target(action))
+            .map(|(entity, _)| entity)
+            .expect("button not found");
+        world.entity_mut(button).insert(Interaction::Clicked);
+        self.update();
+        self.update();
+    }
+
+    fn send_key(&mut self, key: KeyCode, state: ButtonState) {
+        self.app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(key),
+            state,
+        });
+    }
+
+    fn hold(&mut self, key: KeyCode) {
+        self.send_key(key, ButtonState::Pressed);
+        self.update();
+        self.update();
+    }
+
+    fn release(&mut self, key: KeyCode) {
+        self.send_key(key, ButtonState::Released);
+        self.update();
+        self.update();
+    }
+
+    fn press(&mut self, key: KeyCode) {
+        self.send_key(key, ButtonState::Pressed);
+        self.update();
+        self.release(key);
+    }
+
+    fn head(&mut self) -> Point {
+        let world = &mut self.app.world;
+        let children = world
+            .query_filtered::<&Children, With<Snake>>()
+            .single(world);
+        *world.get::<Point>(children[0]).unwrap()
+    }
+
+    // 代替玩家操作：沿最短路径去吃最近的食物
+    fn steer(&mut self) {
+        let world = &mut self.app.world;
+        let food: Vec<Point> = world
+            .query::<&Food>()
+            .iter(world)
+            .map(|food| food.point)
+            .collect();
+        let children = world
+            .query_filtered::<&Children, With<Snake>>()
+            .single(world)
+            .to_vec();
+        let body: Vec<Point> = children
+            .iter()
+            .filter_map(|entity| world.get::<Point>(*entity).copied())
+            .collect();
+        let Some(direction) = next_direction(world.resource::<Level>(), &body, &food) else {
+            return;
+        };
+        world.query::<&mut Snake>().single_mut(world).move_direction = direction;
+    }
+
+    fn snake_length(&mut self) -> usize {
+        let world = &mut self.app.world;
+        let mut query = world.query_filtered::<&Children, With<Snake>>();
+        query.iter(world).map(|children| children.len()).sum()
+    }
+}
+
+const RUNNING: AppState = AppState::InGame(PlayState::Running);
+const PAUSED: AppState = AppState::InGame(PlayState::Paused);
+
+#[test]
+fn main_menu_shows_an_idle_snake() {
+    let mut game = Game::new();
+    assert_eq!(game.app_state(), AppState::MainMenu);
+    assert!(game.count::<OnMainMenuScreen>() > 0);
+    assert_eq!(game.count::<Snake>(), 1);
+    assert_eq!(game.snake_length(), 3);
+
+    // 不在游戏中时蛇不动，也不会生成食物
+    game.run(2.0);
+    assert_eq!(game.app_state(), AppState::MainMenu);
+    assert_eq!(game.count::<Food>(), 0);
+}
+
+#[test]
+fn start_die_and_restart() {
+    let mut game = Game::new();
+    game.click(|action| matches!(action, MenuButtonAction::StartGame(_)));
+    assert_eq!(game.app_state(), RUNNING);
+    assert_eq!(game.count::<OnMainMenuScreen>(), 0);
+    assert_eq!(game.count::<Snake>(), 1);
+
+    // 一秒后生成第一个食物
+    game.run(1.1);
+    assert_eq!(game.count::<Food>(), 1);
+    assert_eq!(game.count::<FoodTimer>(), 0);
+
+    // 一直向前直到撞墙，播放死亡动画后显示游戏结束界面
+    game.run_until(AppState::Dying, 10.0);
+    game.run_until(AppState::GameOver, 2.0);
+    assert!(game.count::<OnGameOverMenuScreen>() > 0);
+
+    game.click(|action| matches!(action, MenuButtonAction::RestartGame));
+    assert_eq!(game.app_state(), RUNNING);
+    assert_eq!(game.count::<OnGameOverMenuScreen>(), 0);
+    // 旧的蛇和食物都已清除，新的一局从头开始
+    assert_eq!(game.count::<Snake>(), 1);
+    assert_eq!(game.snake_length(), 3);
+    assert_eq!(game.count::<Food>(), 0);
+    assert_eq!(game.count::<FoodTimer>(), 1);
+    assert_eq!(game.app.world.resource::<Score>().0, 0);
+
+    game.run(1.1);
+    assert_eq!(game.count::<Food>(), 1);
+
+    // 游戏结束后也可以回到主菜单
+    game.run_until(AppState::GameOver, 12.0);
+    game.click(|action| matches!(action, MenuButtonAction::BackToMainMenu));
+    assert_eq!(game.app_state(), AppState::MainMenu);
+}
+
+#[test]
+fn pause_resume_and_quit_to_main_menu() {
+    let mut game = Game::new();
+    game.click(|action| matches!(action, MenuButtonAction::StartGame(_)));
+    game.run(1.1);
+
+    game.press(KeyCode::Escape);
+    assert_eq!(game.app_state(), PAUSED);
+    assert!(game.count::<OnGamePausedMenuScreen>() > 0);
+
+    // 暂停时蛇不动
+    let head = game.head();
+    game.run(1.0);
+    assert_eq!(game.head(), head);
+
+    game.click(|action| matches!(action, MenuButtonAction::ResumeGame));
+    assert_eq!(game.app_state(), RUNNING);
+    assert_eq!(game.count::<OnGamePausedMenuScreen>(), 0);
+
+    game.press(KeyCode::Space);
+    assert_eq!(game.app_state(), PAUSED);
+    game.click(|action| matches!(action, MenuButtonAction::RestartGame));
+    assert_eq!(game.app_state(), RUNNING);
+    assert_eq!(game.snake_length(), 3);
+
+    game.press(KeyCode::Space);
+    game.click(|action| matches!(action, MenuButtonAction::BackToMainMenu));
+    assert_eq!(game.app_state(), AppState::MainMenu);
+    assert_eq!(game.count::<OnGamePausedMenuScreen>(), 0);
+    assert!(game.count::<OnMainMenuScreen>() > 0);
+    // 回到主菜单时换成一条新的蛇，食物清除
+    assert_eq!(game.count::<Snake>(), 1);
+    assert_eq!(game.snake_length(), 3);
+    assert_eq!(game.count::<Food>(), 0);
+}
+
+// 所有状态
+fn all_states() -> Vec<AppState> {
+    use AppState::*;
+    use PlayState::*;
+    vec![
+        MainMenu,
+        Settings,
+        Achievements,
+        HighScores,
+        ModeSelect,
+        LevelSelect,
+        Editor,
+        InGame(Starting),
+        InGame(Resuming),
+        InGame(Running),
+        InGame(Paused),
+        InGame(Rewinding),
+        Dying,
+        GameOver,
+        LevelComplete,
+    ]
+}
+
+// 只有这一个状态的 App，第一帧之后才能切换
+fn settled(state: AppState) -> App {
+    let mut app = App::new();
+    app.add_state(state);
+    app.update();
+    app
+}
+
+#[test]
+fn every_edge_is_guarded() {
+    use AppState::*;
+    use PlayState::*;
+    let edges = [
+        (MainMenu, Settings),
+        (MainMenu, Achievements),
+        (MainMenu, HighScores),
+        (MainMenu, ModeSelect),
+        (MainMenu, LevelSelect),
+        (MainMenu, Editor),
+        (MainMenu, InGame(Starting)),
+        (MainMenu, InGame(Resuming)),
+        (Settings, MainMenu),
+        (Achievements, MainMenu),
+        (HighScores, MainMenu),
+        (ModeSelect, MainMenu),
+        (ModeSelect, InGame(Starting)),
+        (LevelSelect, MainMenu),
+        (LevelSelect, InGame(Starting)),
+        (Editor, MainMenu),
+        (Editor, InGame(Starting)),
+        (InGame(Starting), InGame(Running)),
+        (InGame(Resuming), InGame(Starting)),
+        (InGame(Resuming), InGame(Paused)),
+        (InGame(Running), InGame(Paused)),
+        (InGame(Running), InGame(Rewinding)),
+        (InGame(Running), Dying),
+        (InGame(Running), LevelComplete),
+        (InGame(Rewinding), InGame(Running)),
+        (InGame(Rewinding), InGame(Paused)),
+        (InGame(Paused), InGame(Running)),
+        (InGame(Paused), InGame(Starting)),
+        (InGame(Paused), MainMenu),
+        (InGame(Paused), Editor),
+        (Dying, GameOver),
+        (GameOver, InGame(Starting)),
+        (GameOver, MainMenu),
+        (GameOver, Editor),
+        (GameOver, LevelSelect),
+        (LevelComplete, InGame(Starting)),
+        (LevelComplete, MainMenu),
+        (LevelComplete, LevelSelect),
+    ];
+    for from in all_states() {
+        for to in all_states() {
+            let allowed = edges.contains(&(from, to));
+            assert_eq!(from.can_transition_to(&to), allowed, "{from:?} -> {to:?}");
+            // 不允许的切换不会 panic，状态保持不变
+            let mut app = settled(from);
+            let mut state = app.world.resource_mut::<State<AppState>>();
+            assert_eq!(state.transition(to), allowed, "{from:?} -> {to:?}");
+            app.update();
+            let expected = if allowed { to } else { from };
+            assert_eq!(*app.world.resource::<State<AppState>>().current(), expected);
+        }
+    }
+}
+
+#[test]
+fn second_transition_in_a_frame_is_ignored() {
+    // 同一帧里撞墙和完成闯关目标，只有第一个生效
+    let mut app = settled(RUNNING);
+    let mut state = app.world.resource_mut::<State<AppState>>();
+    assert!(state.transition(AppState::Dying));
+    assert!(!state.transition(AppState::LevelComplete));
+    assert!(!state.transition(AppState::Dying));
+    app.update();
+    assert_eq!(
+        *app.world.resource::<State<AppState>>().current(),
+        AppState::Dying
+    );
+}
+
+type IsAction = fn(&MenuButtonAction) -> bool;
+
+#[test]
+fn menus_open_and_close() {
+    let mut game = Game::new();
+    let screens: [(IsAction, AppState); 5] = [
+        (
+            |action| matches!(action, MenuButtonAction::OpenSettings),
+            AppState::Settings,
+        ),
+        (
+            |action| matches!(action, MenuButtonAction::OpenAchievements),
+            AppState::Achievements,
+        ),
+        (
+            |action| matches!(action, MenuButtonAction::OpenHighScores),
+            AppState::HighScores,
+        ),
+        (
+            |action| matches!(action, MenuButtonAction::OpenModes),
+            AppState::ModeSelect,
+        ),
+        (
+            |action| matches!(action, MenuButtonAction::OpenCampaign),
+            AppState::LevelSelect,
+        ),
+    ];
+    for (open, state) in screens {
+        game.click(open);
+        assert_eq!(game.app_state(), state);
+        assert_eq!(game.count::<OnMainMenuScreen>(), 0);
+        game.press(KeyCode::Escape);
+        assert_eq!(game.app_state(), AppState::MainMenu);
+        assert!(game.count::<OnMainMenuScreen>() > 0);
+    }
+    assert_eq!(game.count::<OnSettingsMenuScreen>(), 0);
+
+    // 从模式选择和选关界面开始游戏
+    game.click(|action| matches!(action, MenuButtonAction::OpenModes));
+    game.click(|action| matches!(action, MenuButtonAction::StartGame(GameMode::Practice)));
+    assert_eq!(game.app_state(), RUNNING);
+    game.press(KeyCode::Escape);
+    game.click(|action| matches!(action, MenuButtonAction::BackToMainMenu));
+
+    game.click(|action| matches!(action, MenuButtonAction::OpenCampaign));
+    game.click(|action| matches!(action, MenuButtonAction::StartGame(GameMode::Campaign(0))));
+    assert_eq!(game.app_state(), RUNNING);
+}
+
+// 倒退时重新生成的食物共用已有的网格和材质
+#[test]
+fn rewinding_reuses_food_assets() {
+    let mut game = Game::new();
+    let assets = |game: &Game| {
+        let world = &game.app.world;
+        let meshes: HashSet<_> = world.resource::<Assets<Mesh>>().ids().collect();
+        let materials: HashSet<_> = world.resource::<Assets<ColorMaterial>>().ids().collect();
+        (meshes, materials)
+    };
+    game.click(|action| matches!(action, MenuButtonAction::OpenModes));
+    game.click(|action| matches!(action, MenuButtonAction::StartGame(GameMode::Practice)));
+    game.run(1.5);
+    assert_eq!(game.count::<Food>(), 1);
+    let before = assets(&game);
+
+    game.hold(KeyCode::R);
+    assert_eq!(game.app_state(), AppState::InGame(PlayState::Rewinding));
+    game.run(0.5);
+    assert_eq!(assets(&game), before);
+    game.release(KeyCode::R);
+    assert_eq!(game.app_state(), RUNNING);
+}
+
+#[test]
+fn editor_test_play_and_back() {
+    let mut game = Game::new();
+    game.click(|action| matches!(action, MenuButtonAction::OpenEditor));
+    assert_eq!(game.app_state(), AppState::Editor);
+    assert!(game.count::<OnEditorScreen>() > 0);
+    game.press(KeyCode::Escape);
+    assert_eq!(game.app_state(), AppState::MainMenu);
+    assert_eq!(game.count::<OnEditorScreen>(), 0);
+
+    game.click(|action| matches!(action, MenuButtonAction::OpenEditor));
+    game.click(|action| matches!(action, MenuButtonAction::Editor(EditorAction::TestPlay)));
+    assert_eq!(game.app_state(), RUNNING);
+    assert_eq!(game.count::<OnEditorScreen>(), 0);
+
+    game.press(KeyCode::Escape);
+    game.click(|action| matches!(action, MenuButtonAction::BackToEditor));
+    assert_eq!(game.app_state(), AppState::Editor);
+    assert!(game.count::<OnEditorScreen>() > 0);
+
+    // 试玩结束后也可以回到编辑器
+    game.click(|action| matches!(action, MenuButtonAction::Editor(EditorAction::TestPlay)));
+    game.run_until(AppState::GameOver, 12.0);
+    game.click(|action| matches!(action, MenuButtonAction::BackToEditor));
+    assert_eq!(game.app_state(), AppState::Editor);
+}
+
+// 蛇头吃到食物的同一帧障碍也走进这一格，新的蛇头这一帧还没有生成
+#[test]
+fn hazard_meeting_the_head_on_food_is_a_hit() {
+    let mut game = Game::new();
+    let level = Level {
+        hazards: vec![HazardSpec {
+            start: Point::new(-1, 0),
+            motion: HazardMotion::Bounce(Direction::Left),
+            interval: MOVE_INTERVAL,
+        }],
+        ..default()
+    };
+    std::fs::create_dir_all(&game.dir).unwrap();
+    std::fs::write(
+        game.dir.join("custom.level.ron"),
+        ron::to_string(&level).unwrap(),
+    )
+    .unwrap();
+    game.click(|action| matches!(action, MenuButtonAction::OpenEditor));
+    game.click(|action| matches!(action, MenuButtonAction::Editor(EditorAction::Load)));
+    game.click(|action| matches!(action, MenuButtonAction::Editor(EditorAction::TestPlay)));
+    assert_eq!(game.app_state(), RUNNING);
+    game.app.world.spawn(Food {
+        point: Point::new(-3, 0),
+        kind: FoodKind::Normal,
+    });
+
+    // 蛇头和障碍同时走到 (-3, 0)，当帧就要结束
+    while game.count::<Food>() > 0 {
+        assert_eq!(game.app_state(), RUNNING);
+        game.update();
+    }
+    assert_ne!(game.app_state(), RUNNING);
+}
+
+#[test]
+fn settings_are_saved() {
+    let mut game = Game::new();
+    game.click(|action| matches!(action, MenuButtonAction::OpenSettings));
+    game.click(|action| matches!(action, MenuButtonAction::ChangeSetting(Setting::Effects)));
+    game.click(|action| matches!(action, MenuButtonAction::AdjustSetting(Setting::Volume, -1)));
+    game.click(|action| matches!(action, MenuButtonAction::ChangeSetting(Setting::Theme)));
+    game.click(|action| matches!(action, MenuButtonAction::ChangeSetting(Setting::Language)));
+    let saved = std::fs::read(game.dir.join("settings.ron")).unwrap();
+    drop(game);
+
+    // 新的一局在启动前放入刚才保存的设置
+    let game = Game::with_plugins(|_| {
+        let dir = PathBuf::from(std::env::var("SNAKE_DATA_DIR").unwrap());
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("settings.ron"), saved).unwrap();
+    });
+    let settings = game.app.world.resource::<Settings>();
+    assert!(!settings.effects);
+    assert_eq!(settings.volume, Settings::default().volume - 1);
+    assert_eq!(settings.theme, 1);
+    assert_eq!(settings.language, 1);
+}
+
+#[test]
+fn continue_saved_game() {
+    let mut game = Game::new();
+    game.click(|action| matches!(action, MenuButtonAction::StartGame(_)));
+    game.run(1.1);
+    // 暂停时保存
+    game.press(KeyCode::Escape);
+    game.click(|action| matches!(action, MenuButtonAction::BackToMainMenu));
+    let head = game.head();
+
+    // 继续后先处于暂停状态
+    game.click(|action| matches!(action, MenuButtonAction::ContinueGame));
+    game.run_until(PAUSED, 1.0);
+    assert!(game.count::<OnGamePausedMenuScreen>() > 0);
+    assert_ne!(game.head(), head);
+    game.click(|action| matches!(action, MenuButtonAction::ResumeGame));
+    assert_eq!(game.app_state(), RUNNING);
+
+    // 存档读取失败时开始新的一局
+    game.press(KeyCode::Escape);
+    game.click(|action| matches!(action, MenuButtonAction::BackToMainMenu));
+    std::fs::remove_file(game.dir.join("savegame.ron")).unwrap();
+    game.click(|action| matches!(action, MenuButtonAction::ContinueGame));
+    game.run_until(RUNNING, 1.0);
+    assert_eq!(game.snake_length(), 3);
+    assert_eq!(game.count::<Food>(), 0);
+}
+
+#[test]
+fn practice_rewind() {
+    let mut game = Game::new();
+    game.click(|action| matches!(action, MenuButtonAction::OpenModes));
+    game.click(|action| matches!(action, MenuButtonAction::StartGame(GameMode::Practice)));
+    game.run(1.0);
+
+    game.hold(KeyCode::R);
+    assert_eq!(game.app_state(), AppState::InGame(PlayState::Rewinding));
+    game.release(KeyCode::R);
+    assert_eq!(game.app_state(), RUNNING);
+
+    // 倒退时也可以暂停
+    game.run(0.5);
+    game.hold(KeyCode::R);
+    game.press(KeyCode::Escape);
+    assert_eq!(game.app_state(), PAUSED);
+    game.release(KeyCode::R);
+    assert_eq!(game.app_state(), PAUSED);
+    game.click(|action| matches!(action, MenuButtonAction::ResumeGame));
+    assert_eq!(game.app_state(), RUNNING);
+}
+
+#[test]
+fn campaign_level_complete() {
+    let mut game = Game::new();
+    let complete = |game: &mut Game| {
+        // 第一关的目标是吃到 5 个食物
+        game.app
+            .world
+            .resource_mut::<RunStats>()
+            .food_eaten
+            .insert(FoodKind::Normal, 5);
+        game.run_until(AppState::LevelComplete, 0.5);
+    };
+    game.click(|action| matches!(action, MenuButtonAction::OpenCampaign));
+    game.click(|action| matches!(action, MenuButtonAction::StartGame(GameMode::Campaign(0))));
+    complete(&mut game);
+
+    game.click(|action| matches!(action, MenuButtonAction::RestartGame));
+    assert_eq!(game.app_state(), RUNNING);
+    complete(&mut game);
+
+    game.click(|action| matches!(action, MenuButtonAction::OpenCampaign));
+    assert_eq!(game.app_state(), AppState::LevelSelect);
+    game.click(|action| matches!(action, MenuButtonAction::StartGame(GameMode::Campaign(0))));
+    assert_eq!(game.app_state(), RUNNING);
+    complete(&mut game);
+
+    game.click(|action| matches!(action, MenuButtonAction::BackToMainMenu));
+    assert_eq!(game.app_state(), AppState::MainMenu);
+}
+
+// 用过调试控制台的一局照常过关，但不记录进度和成绩
+#[test]
+fn uncounted_campaign_runs_are_not_recorded() {
+    let mut game = Game::new();
+    game.click(|action| matches!(action, MenuButtonAction::OpenCampaign));
+    game.click(|action| matches!(action, MenuButtonAction::StartGame(GameMode::Campaign(0))));
+    let world = &mut game.app.world;
+    world.resource_mut::<CurrentRun>().counted = false;
+    world.resource_mut::<Score>().0 = 7;
+    world
+        .resource_mut::<RunStats>()
+        .food_eaten
+        .insert(FoodKind::Normal, 5);
+    game.run_until(AppState::LevelComplete, 0.5);
+
+    let world = &game.app.world;
+    let table = GameMode::Campaign(0).table();
+    assert_eq!(world.resource::<HighScores>().best(&table), 0);
+    assert_eq!(world.resource::<Record>().0, 0);
+    assert!(!game.dir.join("campaign.ron").exists());
+}
+
+// 帧间隔不固定时，游戏中记录下来的一局也能通过校验
+#[test]
+fn uncounted_runs_do_not_unlock_achievements() {
+    let mut game = Game::new();
+    game.click(|action| matches!(action, MenuButtonAction::StartGame(_)));
+    let world = &mut game.app.world;
+    world.resource_mut::<CurrentRun>().counted = false;
+    world.resource_mut::<Score>().0 = 1000;
+    game.run(0.5);
+    assert_eq!(game.count::<AchievementToast>(), 0);
+
+    // 计入的一局达到目标就会解锁
+    game.app.world.resource_mut::<CurrentRun>().counted = true;
+    game.run(0.5);
+    assert_eq!(game.count::<AchievementToast>(), 1);
+}
+
+#[test]
+fn recorded_runs_pass_verification() {
+    let mut frames = ChaCha8Rng::seed_from_u64(47);
+    for mode in [
+        GameMode::Classic,
+        GameMode::Portals,
+        GameMode::Hazards,
+        GameMode::Shrinking,
+        GameMode::TimeAttack(60),
+        GameMode::Campaign(0),
+    ] {
+        let mut game = Game::new();
+        match mode {
+            GameMode::Classic => {}
+            GameMode::Campaign(_) => {
+                game.click(|action| matches!(action, MenuButtonAction::OpenCampaign))
+            }
+            _ => game.click(|action| matches!(action, MenuButtonAction::OpenModes)),
+        }
+        game.click(|action| matches!(action, MenuButtonAction::StartGame(m) if *m == mode));
+        assert_eq!(game.app_state(), RUNNING);
+        while game.app_state() == RUNNING && game.app.world.resource::<Score>().0 < 30 {
+            game.steer();
+            game.update_by(Duration::from_millis(frames.gen_range(5..=70)));
+        }
+
+        let world = &game.app.world;
+        let replay = world
+            .resource::<RunRecorder>()
+            .replay(world.resource::<CurrentRun>(), world.resource::<Score>().0)
+            .unwrap();
+        assert!(!replay.steps.is_empty(), "{mode:?}");
+        assert_eq!(verify(&replay, &Campaign::default()), Ok(()), "{mode:?}");
+    }
+}
+
+#[test]
+fn mashing_keys_never_panics() {
+    let mut game = Game::new();
+    game.click(|action| matches!(action, MenuButtonAction::StartGame(_)));
+    // 同一帧按下暂停和返回，游戏中和暂停菜单里都会同时要求切换
+    for _ in 0..40 {
+        game.send_key(KeyCode::Space, ButtonState::Pressed);
+        game.send_key(KeyCode::Escape, ButtonState::Pressed);
+        game.update();
+        game.send_key(KeyCode::Space, ButtonState::Released);
+        game.send_key(KeyCode::Escape, ButtonState::Released);
+        game.update();
+        assert!(matches!(game.app_state(), RUNNING | PAUSED));
+    }
+    // 撞墙的同时也在按键
+    for _ in 0..400 {
+        if game.app_state() == AppState::Dying {
+            break;
+        }
+        if game.app_state() == PAUSED {
+            game.click(|action| matches!(action, MenuButtonAction::ResumeGame));
+        }
+        game.press(KeyCode::Escape);
+    }
+    game.run_until(AppState::GameOver, 2.0);
+}
+
+#[cfg(feature = "debug")]
+#[test]
+fn console_commands() {
+    use snake::debug::{Console, ConsoleCommand, ConsolePlugin};
+
+    let mut game = Game::with_plugins(|app| {
+        app.add_plugin(ConsolePlugin);
+    });
+    let run = |game: &mut Game, text: &str| {
+        let command: ConsoleCommand = text.parse().unwrap();
+        game.app.world.send_event(command);
+        game.update();
+    };
+    assert!("grow".parse::<ConsoleCommand>().is_err());
+    assert!("food 1 x".parse::<ConsoleCommand>().is_err());
+    assert!("speed 0".parse::<ConsoleCommand>().is_err());
+    assert!("fly".parse::<ConsoleCommand>().is_err());
+
+    // 只能走允许的切换
+    run(&mut game, "state gameover");
+    assert_eq!(game.app_state(), AppState::MainMenu);
+    run(&mut game, "seed 42");
+    run(&mut game, "state start");
+    game.update();
+    assert_eq!(game.app_state(), RUNNING);
+    let run_info = game.app.world.resource::<snake::mode::CurrentRun>();
+    assert_eq!(run_info.seed, 42);
+    assert!(!run_info.counted);
+
+    run(&mut game, "grow 10");
+    game.run(2.0);
+    assert_eq!(game.snake_length(), 13);
+
+    let head = game.head();
+    let ahead = Point::new(head.x + 2, head.y);
+    run(&mut game, &format!("food {} {} golden", ahead.x, ahead.y));
+    assert!(game.count::<Food>() >= 1);
+    run(&mut game, &format!("food {} {}", head.x, head.y));
+    let console = game.app.world.resource::<Console>();
+    assert!(console
+        .lines()
+        .last()
+        .unwrap()
+        .starts_with("cannot place food"));
+
+    // 无敌时撞墙停住
+    run(&mut game, "invincible");
+    run(&mut game, "speed 20");
+    game.run(5.0);
+    assert_eq!(game.app_state(), RUNNING);
+    run(&mut game, "invincible");
+    game.run_until(AppState::Dying, 1.0);
+}
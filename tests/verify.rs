@@ -0,0 +1,114 @@
+mod common;
+
+use common::{play, play_daily};
+use snake::{
+    campaign::Campaign,
+    mode::{seed_from_str, GameMode},
+    verify::{verify, VerifyError},
+};
+
+#[test]
+fn honest_replays_are_accepted() {
+    let campaign = Campaign::default();
+    for mode in [
+        GameMode::Classic,
+        GameMode::Portals,
+        GameMode::Hazards,
+        GameMode::Shrinking,
+        GameMode::TimeAttack(60),
+    ] {
+        let replay = play(mode, 1, 20);
+        assert!(replay.score > 0, "{mode:?}");
+        assert_eq!(verify(&replay, &campaign), Ok(()), "{mode:?}");
+    }
+    let mode = GameMode::Campaign(0);
+    let replay = play(mode, seed_from_str(&mode.table()), u32::MAX);
+    assert_eq!(verify(&replay, &campaign), Ok(()));
+    let replay = play_daily("2023-02-01", 20);
+    assert!(replay.score > 0);
+    assert_eq!(verify(&replay, &campaign), Ok(()));
+}
+
+#[test]
+fn tampered_replays_are_rejected() {
+    let campaign = Campaign::default();
+    let replay = play(GameMode::Classic, 7, 10);
+
+    let mut higher = replay.clone();
+    higher.score += 1;
+    assert_eq!(
+        verify(&higher, &campaign),
+        Err(VerifyError::ScoreMismatch {
+            claimed: replay.score + 1,
+            min: replay.score,
+            max: replay.score,
+        })
+    );
+
+    let mut eaten = replay.clone();
+    eaten.eaten.pop();
+    assert_eq!(verify(&eaten, &campaign), Err(VerifyError::EatenMismatch));
+
+    // 掉头撞到自己
+    let mut crashed = replay.clone();
+    let last = *crashed.steps.last().unwrap();
+    crashed.steps.push(last.opposite());
+    assert_eq!(
+        verify(&crashed, &campaign),
+        Err(VerifyError::Crashed(replay.steps.len()))
+    );
+
+    let mut reseeded = replay;
+    reseeded.seed += 1;
+    assert!(verify(&reseeded, &campaign).is_err());
+}
+
+#[test]
+fn steps_after_the_deadline_are_rejected() {
+    let campaign = Campaign::default();
+    let mut replay = play(GameMode::TimeAttack(60), 3, u32::MAX);
+    assert_eq!(verify(&replay, &campaign), Ok(()));
+    let last = *replay.steps.last().unwrap();
+    replay.steps.push(last);
+    assert_eq!(
+        verify(&replay, &campaign),
+        Err(VerifyError::AfterEnd(replay.steps.len() - 1))
+    );
+}
+
+#[test]
+fn unranked_runs_are_rejected() {
+    let campaign = Campaign::default();
+    let mut replay = play(GameMode::Classic, 7, 3);
+    replay.mode = GameMode::Practice;
+    assert_eq!(
+        verify(&replay, &campaign),
+        Err(VerifyError::Unranked(GameMode::Practice))
+    );
+    replay.mode = GameMode::Campaign(0);
+    assert_eq!(verify(&replay, &campaign), Err(VerifyError::WrongSeed));
+    replay.mode = GameMode::Campaign(999);
+    assert_eq!(
+        verify(&replay, &campaign),
+        Err(VerifyError::UnknownLevel(999))
+    );
+}
+
+// 每日挑战不能换成别的种子
+#[test]
+fn daily_replays_must_use_the_date_seed() {
+    let campaign = Campaign::default();
+    let replay = play_daily("2023-02-01", 5);
+
+    let mut reseeded = play(GameMode::Daily, 1, 5);
+    reseeded.date = replay.date.clone();
+    assert_eq!(verify(&reseeded, &campaign), Err(VerifyError::WrongSeed));
+
+    let mut redated = replay.clone();
+    redated.date = Some("2023-02-02".to_string());
+    assert_eq!(verify(&redated, &campaign), Err(VerifyError::WrongSeed));
+
+    let mut undated = replay;
+    undated.date = None;
+    assert_eq!(verify(&undated, &campaign), Err(VerifyError::WrongSeed));
+}